@@ -25,6 +25,7 @@ speculate! {
             use markup5ever_rcdom::RcDom;
             use html5ever::tendril::stream::TendrilSink;
 
+            #[allow(clippy::let_and_return)]
             b.iter(|| {
                 let rc_dom = parse_document(RcDom::default(),
                                             Default::default()).one(str);
@@ -41,8 +42,8 @@ speculate! {
                 let document = Document::from(str);
             }
 
-            bench "Any (11446 Nodes)" |b| {
-                assert_eq!(document.find(Any).count(), 11446);
+            bench "Any (11447 Nodes)" |b| {
+                assert_eq!(document.find(Any).count(), 11447);
                 b.iter(|| document.find(Any).count());
             }
 
@@ -82,6 +83,47 @@ speculate! {
         }
     }
 
+    context "Node::find() from <body>" {
+        before {
+            let document = Document::from(include_str!("../tests/fixtures/struct.Vec.html"));
+            let body = document.find(Name("body")).next().unwrap();
+        }
+
+        bench "Any (11424 Nodes)" |b| {
+            assert_eq!(body.find(Any).count(), 11424);
+            b.iter(|| body.find(Any).count());
+        }
+
+        bench "Comment (1 Node)" |b| {
+            assert_eq!(body.find(Comment).count(), 1);
+            b.iter(|| body.find(Comment).count());
+        }
+    }
+
+    context "Selection::find()" {
+        before {
+            let document = Document::from(include_str!("../tests/fixtures/struct.Vec.html"));
+        }
+
+        bench "Any from <body> (11424 Nodes)" |b| {
+            let body = document.find(Name("body")).into_selection();
+            assert_eq!(body.find(Any).len(), 11424);
+            b.iter(|| body.find(Any).len());
+        }
+
+        bench "Comment from <body> (1 Node)" |b| {
+            let body = document.find(Name("body")).into_selection();
+            assert_eq!(body.find(Comment).len(), 1);
+            b.iter(|| body.find(Comment).len());
+        }
+
+        bench "Name(\"span\") from 208 nested <div>s (1711 Nodes)" |b| {
+            let divs = document.find(Name("div")).into_selection();
+            assert_eq!(divs.find(Name("span")).len(), 1711);
+            b.iter(|| divs.find(Name("span")).len());
+        }
+    }
+
     context "Node::attr()" {
         before {
             let html = "<div a=b c=d e=f g=h i=j k=l m=n o=p q=r s=t u=v w=x y=z>";
@@ -1,43 +1,274 @@
-use html5ever::tendril::{ByteTendril, ReadExt, StrTendril};
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_8};
+use html5ever::tendril::stream::TendrilSink;
+use html5ever::tendril::{fmt, ByteTendril, ReadExt, StrTendril};
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+    TokenizerResult,
+};
+use html5ever::tree_builder::{
+    create_element, ElementFlags, NodeOrText, QuirksMode, TreeBuilder, TreeBuilderOpts, TreeSink,
+};
+use html5ever::{local_name, namespace_url, ns, Attribute, ExpandedName, LocalName, QualName};
 
-use crate::node::{self, Node};
+use crate::encoding;
+use crate::node::{self, Node, Span};
 use crate::predicate::Predicate;
 use crate::selection::Selection;
+use crate::selector::{self, Selector};
 
-use std::io;
+use bit_set::BitSet;
 
-/// An HTML document.
-#[derive(Clone, Debug, PartialEq, Eq)]
+use std::borrow::Cow;
+use std::{io, mem};
+
+/// An HTML or XML document.
+///
+/// Besides its nodes, a Document keeps what was recorded while parsing it, so
+/// it can no longer be built with a struct literal. Use
+/// `Document::from(Vec<node::Raw>)` to build one from nodes directly. Two
+/// Documents are equal if their nodes are.
+#[derive(Clone, Debug)]
 pub struct Document {
     pub nodes: Vec<node::Raw>,
+    errors: Vec<HtmlError>,
+    source: Option<Source>,
+}
+
+// The input a document was parsed from, and the span of each node in it.
+#[derive(Clone, Debug)]
+struct Source {
+    text: StrTendril,
+    spans: Vec<Option<Span>>,
+}
+
+/// Options for `Document::parse_with`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOpts {
+    /// Parse as if scripting were enabled, which makes the contents of
+    /// `<noscript>` a single text node. Defaults to true.
+    pub scripting_enabled: bool,
+    /// Parse as an iframe `srcdoc` document. Defaults to false.
+    pub iframe_srcdoc: bool,
+    /// Report every parse error with a detailed message, at some performance
+    /// cost. Defaults to false.
+    pub exact_errors: bool,
+    /// Drop text nodes containing only whitespace. Defaults to false.
+    pub drop_whitespace: bool,
+    /// Record where each node is in the input, for `Node::source_span` and
+    /// `Node::source_html`. This keeps a copy of the input in the `Document`
+    /// and makes parsing slower. Defaults to false.
+    pub source_spans: bool,
+}
+
+impl Default for ParseOpts {
+    fn default() -> ParseOpts {
+        ParseOpts {
+            scripting_enabled: true,
+            iframe_srcdoc: false,
+            exact_errors: false,
+            drop_whitespace: false,
+            source_spans: false,
+        }
+    }
+}
+
+impl ParseOpts {
+    fn tree_builder(&self) -> TreeBuilderOpts {
+        TreeBuilderOpts {
+            exact_errors: self.exact_errors,
+            scripting_enabled: self.scripting_enabled,
+            iframe_srcdoc: self.iframe_srcdoc,
+            ..Default::default()
+        }
+    }
+
+    fn tokenizer(&self) -> TokenizerOpts {
+        TokenizerOpts {
+            exact_errors: self.exact_errors,
+            ..Default::default()
+        }
+    }
+
+    fn sink(&self) -> Sink {
+        Sink {
+            drop_whitespace: self.drop_whitespace,
+            source_spans: self.source_spans,
+            ..Sink::default()
+        }
+    }
+}
+
+/// An error reported by the HTML parser. Parsing always produces a `Document`,
+/// but these errors indicate malformed input.
+///
+/// html5ever only reports the line on which an error occurred, so errors have
+/// no column or byte offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HtmlError {
+    line: u64,
+    message: Cow<'static, str>,
 }
 
+impl HtmlError {
+    /// Get the 1-based line of the input at which the error was reported.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Get a description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for HtmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl std::error::Error for HtmlError {}
+
+impl PartialEq for Document {
+    fn eq(&self, other: &Document) -> bool {
+        self.nodes == other.nodes
+    }
+}
+
+impl Eq for Document {}
+
 impl Document {
     /// Returns a `Selection` containing nodes passing the given predicate `p`.
+    ///
+    /// The contents of `<template>` elements are skipped, like they are by
+    /// selectors in browsers.
+    #[allow(unknown_lints, mismatched_lifetime_syntaxes)]
     pub fn find<P: Predicate>(&self, predicate: P) -> Find<P> {
         Find {
             document: self,
             next: 0,
             predicate,
+            in_templates: false,
+        }
+    }
+
+    /// Like `find`, but also searches the contents of `<template>` elements.
+    pub fn find_in_templates<P: Predicate>(&self, predicate: P) -> Find<'_, P> {
+        Find {
+            in_templates: true,
+            ..self.find(predicate)
         }
     }
 
+    /// Returns the nodes matching the CSS selector `selector`, or an error if
+    /// `selector` could not be parsed.
+    pub fn select(&self, selector: &str) -> Result<Find<'_, Selector>, selector::ParseError> {
+        Ok(self.find(Selector::parse(selector)?))
+    }
+
     /// Returns the `n`th node of the document as a `Some(Node)`, indexed from
     /// 0, or `None` if n is greater than or equal to the number of nodes.
+    #[allow(unknown_lints, mismatched_lifetime_syntaxes)]
     pub fn nth(&self, n: usize) -> Option<Node> {
         Node::new(self, n)
     }
 
-    pub fn from_read<R: io::Read>(mut readable: R) -> io::Result<Document> {
+    /// Returns the errors reported while parsing this document.
+    pub fn errors(&self) -> &[HtmlError] {
+        &self.errors
+    }
+
+    pub(crate) fn source(&self) -> Option<&str> {
+        self.source.as_ref().map(|source| &*source.text)
+    }
+
+    pub(crate) fn span(&self, index: usize) -> Option<Span> {
+        self.source.as_ref()?.spans[index]
+    }
+
+    /// Parses `input` into a `Document` using the given options.
+    pub fn parse_with<T: Into<StrTendril>>(opts: ParseOpts, input: T) -> Document {
+        let tree_builder = TreeBuilder::new(opts.sink(), opts.tree_builder());
+        let mut driver = Driver::new(tree_builder, opts.tokenizer());
+        driver.feed(input.into());
+        driver.finish()
+    }
+
+    /// Parses `html` as a fragment, the way it would be parsed as the contents
+    /// of a `context` element (e.g. `"tr"` or `"ul"`), instead of wrapping it
+    /// in `html`, `head` and `body` elements. The top level nodes of the
+    /// fragment have no parent.
+    pub fn from_fragment(html: &str, context: &str) -> Document {
+        Document::from_fragment_with(ParseOpts::default(), html, context)
+    }
+
+    /// Parses `html` as a fragment in a `context` element like
+    /// `Document::from_fragment`, using the given options.
+    pub fn from_fragment_with(opts: ParseOpts, html: &str, context: &str) -> Document {
+        let mut sink = Sink {
+            fragment: true,
+            ..opts.sink()
+        };
+        let context = QualName::new(None, ns!(html), LocalName::from(context));
+        let context = create_element(&mut sink, context, vec![]);
+        let tree_builder = TreeBuilder::new_for_fragment(sink, context, None, opts.tree_builder());
+        let tokenizer_opts = TokenizerOpts {
+            initial_state: Some(tree_builder.tokenizer_state_for_context_elem()),
+            ..opts.tokenizer()
+        };
+        let mut driver = Driver::new(tree_builder, tokenizer_opts);
+        driver.feed(StrTendril::from(html));
+        driver.finish()
+    }
+
+    /// Parses `xml` with an XML parser instead of an HTML one. Element and
+    /// attribute names are case-sensitive and keep their namespace prefixes,
+    /// and CDATA sections become text nodes.
+    ///
+    /// `Node::html` still serializes nodes as HTML, which drops namespace
+    /// prefixes and declarations. Use `Node::xml` to serialize them as XML.
+    pub fn from_xml(xml: &str) -> Document {
+        xml5ever::driver::parse_document(Sink::default(), Default::default()).one(xml)
+    }
+
+    /// Reads and parses an HTML document, determining its character encoding
+    /// with `encoding::sniff`.
+    pub fn from_read<R: io::Read>(readable: R) -> io::Result<Document> {
+        Document::from_read_with_charset(readable, None).map(|(document, _)| document)
+    }
+
+    /// Reads and parses an HTML document, determining its character encoding
+    /// with `encoding::sniff` using `charset` as the transport layer encoding
+    /// label (e.g. from a `Content-Type` header). Returns the `Document` along
+    /// with the encoding that was used to decode it.
+    pub fn from_read_with_charset<R: io::Read>(
+        mut readable: R,
+        charset: Option<&str>,
+    ) -> io::Result<(Document, &'static Encoding)> {
         let mut byte_tendril = ByteTendril::new();
         readable.read_to_tendril(&mut byte_tendril)?;
 
-        match byte_tendril.try_reinterpret() {
-            Ok(str_tendril) => Ok(Document::from(str_tendril)),
-            Err(_) => Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "stream did not contain valid UTF-8",
-            )),
+        let encoding = encoding::sniff(&byte_tendril, charset);
+        // Avoid copying the input if it is valid UTF-8 without a BOM.
+        if encoding == UTF_8 && !byte_tendril.starts_with(b"\xEF\xBB\xBF") {
+            match byte_tendril.try_reinterpret() {
+                Ok(str_tendril) => return Ok((Document::from(str_tendril), encoding)),
+                Err(bytes) => byte_tendril = bytes,
+            }
+        }
+
+        let (string, _) = encoding.decode_with_bom_removal(&byte_tendril);
+        Ok((Document::from(&*string), encoding))
+    }
+}
+
+impl From<Vec<node::Raw>> for Document {
+    /// Builds a `Document` from nodes, with no parse errors or source spans.
+    fn from(nodes: Vec<node::Raw>) -> Document {
+        Document {
+            nodes,
+            errors: Vec::new(),
+            source: None,
         }
     }
 }
@@ -45,96 +276,728 @@ impl Document {
 impl From<StrTendril> for Document {
     /// Parses the given `StrTendril` into a `Document`.
     fn from(tendril: StrTendril) -> Document {
-        use html5ever::parse_document;
-        use html5ever::tendril::stream::TendrilSink;
-        use markup5ever_rcdom::{Handle, NodeData, RcDom};
-
-        let mut document = Document { nodes: vec![] };
-
-        let rc_dom = parse_document(RcDom::default(), Default::default()).one(tendril);
-        recur(&mut document, &rc_dom.document, None, None);
-        return document;
-
-        fn recur(
-            document: &mut Document,
-            node: &Handle,
-            parent: Option<usize>,
-            prev: Option<usize>,
-        ) -> Option<usize> {
-            match node.data {
-                NodeData::Document => {
-                    let mut prev = None;
-                    for child in node.children.borrow().iter() {
-                        prev = recur(document, child, None, prev)
-                    }
-                    None
+        Document::parse_with(ParseOpts::default(), tendril)
+    }
+}
+
+/// Incrementally parses an HTML document from chunks of bytes.
+///
+/// The character encoding is determined with `encoding::sniff` from the first
+/// 1024 bytes, after which every chunk is decoded and parsed as it arrives.
+/// Call `TendrilSink::finish` to obtain the `Document`.
+pub struct DocumentBuilder {
+    driver: Driver,
+    charset: Option<String>,
+    buffer: Vec<u8>,
+    decoder: Option<Decoder>,
+}
+
+impl DocumentBuilder {
+    /// Create a DocumentBuilder which sniffs the encoding of its input.
+    pub fn new() -> DocumentBuilder {
+        DocumentBuilder {
+            driver: Driver::new(
+                TreeBuilder::new(Sink::default(), Default::default()),
+                Default::default(),
+            ),
+            charset: None,
+            buffer: Vec::new(),
+            decoder: None,
+        }
+    }
+
+    /// Create a DocumentBuilder using `charset` as the transport layer
+    /// encoding label (e.g. from a `Content-Type` header).
+    pub fn with_charset(charset: &str) -> DocumentBuilder {
+        DocumentBuilder {
+            charset: Some(charset.into()),
+            ..DocumentBuilder::new()
+        }
+    }
+
+    /// Get the encoding used to decode the input, or None if not enough input
+    /// has been processed yet to determine it.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        self.decoder.as_ref().map(|decoder| decoder.encoding())
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) {
+        let decoder = match self.decoder {
+            Some(ref mut decoder) => decoder,
+            None => {
+                let encoding = encoding::sniff(bytes, self.charset.as_deref());
+                self.decoder.insert(encoding.new_decoder_with_bom_removal())
+            }
+        };
+
+        let length = decoder
+            .max_utf8_buffer_length(bytes.len())
+            .expect("chunk too large to decode");
+        let mut string = String::with_capacity(length);
+        let (result, _, _) = decoder.decode_to_string(bytes, &mut string, last);
+        debug_assert_eq!(result, CoderResult::InputEmpty);
+
+        if !string.is_empty() {
+            self.driver.feed(StrTendril::from(string));
+        }
+    }
+}
+
+impl Default for DocumentBuilder {
+    fn default() -> DocumentBuilder {
+        DocumentBuilder::new()
+    }
+}
+
+impl std::fmt::Debug for DocumentBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentBuilder")
+            .field("charset", &self.charset)
+            .field("encoding", &self.encoding())
+            // driver does not implement Debug
+            .finish()
+    }
+}
+
+impl TendrilSink<fmt::Bytes> for DocumentBuilder {
+    type Output = Document;
+
+    fn process(&mut self, tendril: ByteTendril) {
+        if self.decoder.is_some() {
+            self.decode(&tendril, false);
+        } else {
+            self.buffer.extend_from_slice(&tendril);
+            if self.buffer.len() >= encoding::PRESCAN_LENGTH {
+                let buffer = mem::take(&mut self.buffer);
+                self.decode(&buffer, false);
+            }
+        }
+    }
+
+    fn error(&mut self, desc: Cow<'static, str>) {
+        self.driver
+            .tokenizer
+            .sink
+            .tree_builder
+            .sink
+            .parse_error(desc);
+    }
+
+    fn finish(mut self) -> Document {
+        let buffer = mem::take(&mut self.buffer);
+        self.decode(&buffer, true);
+        self.driver.finish()
+    }
+}
+
+/// Feeds input to the tokenizer. When recording source spans, it is fed one
+/// tag or run of text at a time, so that the tokens emitted for each piece can
+/// be attributed to their location in the input.
+struct Driver {
+    tokenizer: Tokenizer<Tracker>,
+    queue: BufferQueue,
+}
+
+impl Driver {
+    fn new(tree_builder: TreeBuilder<usize, Sink>, opts: TokenizerOpts) -> Driver {
+        let tracker = Tracker {
+            tree_builder,
+            input: StrTendril::new(),
+            fed: 0,
+            position: 0,
+            line: 1,
+            column: 1,
+        };
+        Driver {
+            tokenizer: Tokenizer::new(tracker, opts),
+            queue: BufferQueue::new(),
+        }
+    }
+
+    fn feed(&mut self, chunk: StrTendril) {
+        if !self.tokenizer.sink.tree_builder.sink.source_spans {
+            self.queue.push_back(chunk);
+            while let TokenizerResult::Script(_) = self.tokenizer.feed(&mut self.queue) {}
+            return;
+        }
+
+        let offset = self.tokenizer.sink.input.len();
+        self.tokenizer.sink.input.push_tendril(&chunk);
+
+        let bytes = chunk.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            // Pieces end after every `<` and `>`. Text followed by a `<` is
+            // only emitted once the `<` has been seen, but does not include it.
+            let end = bytes[start..]
+                .iter()
+                .position(|&b| b == b'<' || b == b'>')
+                .map_or(bytes.len(), |i| start + i + 1);
+            self.tokenizer.sink.fed = match bytes[end - 1] {
+                b'<' => offset + end - 1,
+                _ => offset + end,
+            };
+            self.queue
+                .push_back(chunk.subtendril(start as u32, (end - start) as u32));
+            while let TokenizerResult::Script(_) = self.tokenizer.feed(&mut self.queue) {}
+            start = end;
+        }
+    }
+
+    fn finish(mut self) -> Document {
+        self.tokenizer.end();
+        let Tracker {
+            tree_builder,
+            input,
+            ..
+        } = self.tokenizer.sink;
+        let mut document = tree_builder.sink.finish();
+        if let Some(ref mut source) = document.source {
+            close_spans(&document.nodes, &mut source.spans, &input);
+            source.text = input;
+        }
+        document
+    }
+}
+
+// Extend the spans of elements, which so far only cover their start tags, over
+// their contents and end tags. Children come after their parents in document
+// order, so they are extended first.
+fn close_spans(nodes: &[node::Raw], spans: &mut [Option<Span>], input: &str) {
+    for index in (0..nodes.len()).rev() {
+        let end = match nodes[index].last_child.and_then(|child| spans[child]) {
+            Some(child) => child.end,
+            None => 0,
+        };
+        if let (Some(span), node::Data::Element(name, _)) = (&mut spans[index], &nodes[index].data)
+        {
+            span.end = span.end.max(end);
+
+            // Whitespace before the end tag is not a child when the parser
+            // drops whitespace-only text, so skip over it here.
+            let rest = &input.as_bytes()[span.end..];
+            let whitespace = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            let rest = &rest[whitespace..];
+            let name = name.local.as_bytes();
+            let end_tag = rest.starts_with(b"</")
+                && rest.len() > name.len() + 2
+                && rest[2..name.len() + 2].eq_ignore_ascii_case(name)
+                && matches!(
+                    rest[name.len() + 2],
+                    b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0C'
+                );
+            if end_tag {
+                if let Some(length) = rest.iter().position(|&b| b == b'>') {
+                    span.end += whitespace + length + 1;
+                }
+            }
+        }
+    }
+}
+
+/// A `TokenSink` which tells the `Sink` where in the input each token it
+/// forwards to the tree builder came from.
+///
+/// Tokens are contiguous in the input, so each one spans from the end of the
+/// previous one to the end of the input fed to the tokenizer when it was
+/// emitted.
+struct Tracker {
+    tree_builder: TreeBuilder<usize, Sink>,
+    input: StrTendril,
+    // The end of the input fed to the tokenizer so far.
+    fed: usize,
+    // The end of the last token, and its line and column.
+    position: usize,
+    line: u64,
+    column: usize,
+}
+
+impl Tracker {
+    // Consume the input up to `end` as the next token.
+    fn advance(&mut self, end: usize) -> Span {
+        let span = Span {
+            line: self.line,
+            column: self.column,
+            start: self.position,
+            end,
+        };
+        let skipped = &self.input[self.position..end];
+        match skipped.rfind('\n') {
+            Some(i) => {
+                self.line += skipped.matches('\n').count() as u64;
+                self.column = skipped[i + 1..].chars().count() + 1;
+            }
+            None => self.column += skipped.chars().count(),
+        }
+        self.position = end;
+        span
+    }
+
+    // Tell the `Sink` where in the input `token` is.
+    fn track(&mut self, token: &Token) {
+        // Several runs of text may be emitted for the same piece of input, so
+        // end them early where possible. Text starting with `&` may come from
+        // a character reference instead.
+        let end = match *token {
+            Token::ParseError(_) => return,
+            Token::CharacterTokens(ref text)
+                if !text.starts_with('&')
+                    && self.input[self.position..self.fed].starts_with(&**text) =>
+            {
+                self.position + text.len()
+            }
+            _ => self.fed,
+        };
+        let span = self.advance(end);
+
+        let sink = &mut self.tree_builder.sink;
+        sink.token = Some(span);
+        sink.start_tag = match *token {
+            Token::TagToken(Tag {
+                kind: TagKind::StartTag,
+                ref name,
+                ..
+            }) => Some(name.clone()),
+            _ => None,
+        };
+        // The tree builder may hold text back and insert it while processing
+        // the next tag (e.g. in tables), so text nodes get the span of the
+        // run of text tokens before it instead.
+        if let Token::CharacterTokens(_) | Token::NullCharacterToken = *token {
+            sink.text = match sink.text {
+                Some(text) if text.end == span.start => Some(Span {
+                    end: span.end,
+                    ..text
+                }),
+                _ => Some(span),
+            };
+        }
+    }
+}
+
+impl TokenSink for Tracker {
+    type Handle = usize;
+
+    fn process_token(&mut self, token: Token, line: u64) -> TokenSinkResult<usize> {
+        if self.tree_builder.sink.source_spans {
+            self.track(&token);
+        }
+        self.tree_builder.process_token(token, line)
+    }
+
+    fn end(&mut self) {
+        self.tree_builder.end();
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.tree_builder
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+/// A `TreeSink` building a `Document`.
+///
+/// Nodes are stored in creation order with links that the tree builder can
+/// freely rearrange, and are renumbered into document order by `finish`.
+struct Sink {
+    nodes: Vec<Entry>,
+    integration_points: BitSet,
+    errors: Vec<HtmlError>,
+    line: u64,
+    // Whether to strip the `html` element a fragment is parsed into.
+    fragment: bool,
+    drop_whitespace: bool,
+    // Whether a `Tracker` reports the span of the token being processed, its
+    // name if it is a start tag, and the span of the last run of text tokens.
+    source_spans: bool,
+    token: Option<Span>,
+    start_tag: Option<LocalName>,
+    text: Option<Span>,
+}
+
+#[derive(Default)]
+struct Entry {
+    parent: Option<usize>,
+    prev: Option<usize>,
+    next: Option<usize>,
+    first_child: Option<usize>,
+    last_child: Option<usize>,
+    // None for the document.
+    data: Option<node::Data>,
+    span: Option<Span>,
+}
+
+impl Default for Sink {
+    fn default() -> Sink {
+        Sink {
+            // The document node.
+            nodes: vec![Entry::default()],
+            integration_points: BitSet::new(),
+            errors: Vec::new(),
+            line: 1,
+            fragment: false,
+            drop_whitespace: false,
+            source_spans: false,
+            token: None,
+            start_tag: None,
+            text: None,
+        }
+    }
+}
+
+impl Sink {
+    fn create(&mut self, data: Option<node::Data>) -> usize {
+        self.nodes.push(Entry {
+            data,
+            span: self.token,
+            ..Entry::default()
+        });
+        self.nodes.len() - 1
+    }
+
+    fn create_text(&mut self, text: StrTendril) -> usize {
+        let index = self.create(Some(node::Data::Text(text)));
+        self.nodes[index].span = self.text;
+        index
+    }
+
+    fn detach(&mut self, target: usize) {
+        let Entry {
+            parent, prev, next, ..
+        } = self.nodes[target];
+
+        match prev {
+            Some(prev) => self.nodes[prev].next = next,
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent].first_child = next;
                 }
-                NodeData::Text { ref contents } => {
-                    let data = node::Data::Text(contents.borrow().clone());
-                    Some(append(document, data, parent, prev))
+            }
+        }
+        match next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent].last_child = prev;
                 }
-                NodeData::Comment { ref contents } => {
-                    let data = node::Data::Comment(contents.clone());
-                    Some(append(document, data, parent, prev))
+            }
+        }
+
+        let entry = &mut self.nodes[target];
+        entry.parent = None;
+        entry.prev = None;
+        entry.next = None;
+    }
+
+    fn append_child(&mut self, parent: usize, child: usize) {
+        let last_child = self.nodes[parent].last_child;
+        match last_child {
+            Some(last_child) => self.nodes[last_child].next = Some(child),
+            None => self.nodes[parent].first_child = Some(child),
+        }
+        self.nodes[parent].last_child = Some(child);
+
+        let entry = &mut self.nodes[child];
+        entry.parent = Some(parent);
+        entry.prev = last_child;
+    }
+
+    fn insert_before(&mut self, sibling: usize, child: usize) {
+        let Entry { parent, prev, .. } = self.nodes[sibling];
+        match prev {
+            Some(prev) => self.nodes[prev].next = Some(child),
+            None => {
+                if let Some(parent) = parent {
+                    self.nodes[parent].first_child = Some(child);
                 }
-                NodeData::Element {
-                    ref name,
-                    ref attrs,
-                    ..
-                } => {
-                    let name = name.clone();
-                    let attrs = attrs
-                        .borrow()
-                        .iter()
-                        .map(|attr| (attr.name.clone(), attr.value.clone()))
-                        .collect();
-                    let data = node::Data::Element(name, attrs);
-                    let index = append(document, data, parent, prev);
-                    let mut prev = None;
-                    for child in node.children.borrow().iter() {
-                        prev = recur(document, child, Some(index), prev)
+            }
+        }
+        self.nodes[sibling].prev = Some(child);
+
+        let entry = &mut self.nodes[child];
+        entry.parent = parent;
+        entry.prev = prev;
+        entry.next = Some(sibling);
+    }
+
+    // Append `text` to `target` if it is a text node.
+    fn append_text(&mut self, target: Option<usize>, text: &StrTendril) -> bool {
+        let entry = match target {
+            Some(target) => &mut self.nodes[target],
+            None => return false,
+        };
+        match entry.data {
+            Some(node::Data::Text(ref mut contents)) => {
+                contents.push_tendril(text);
+                if let (Some(span), Some(text)) = (entry.span.as_mut(), self.text) {
+                    span.end = span.end.max(text.end);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl TreeSink for Sink {
+    type Handle = usize;
+    type Output = Document;
+
+    fn finish(mut self) -> Document {
+        let mut document = Document {
+            nodes: Vec::with_capacity(self.nodes.len()),
+            errors: self.errors,
+            source: None,
+        };
+
+        let mut root = 0;
+        if self.fragment {
+            root = self.nodes[root].first_child.unwrap_or(root);
+        }
+
+        // Walk the tree in document order, without recursion.
+        let mut stack = vec![];
+        let mut spans = vec![];
+        let (mut parent, mut prev) = (None, None);
+        let mut current = self.nodes[root].first_child;
+        loop {
+            while let Some(id) = current {
+                current = self.nodes[id].next;
+                if let Some(data) = self.nodes[id].data.take() {
+                    if self.drop_whitespace {
+                        if let node::Data::Text(ref text) = data {
+                            if text.chars().all(|c| c.is_ascii_whitespace()) {
+                                continue;
+                            }
+                        }
+                    }
+
+                    let index = append(&mut document, data, parent, prev);
+                    if self.source_spans {
+                        spans.push(self.nodes[id].span);
+                    }
+                    prev = Some(index);
+                    if let Some(first_child) = self.nodes[id].first_child {
+                        stack.push((current, parent, prev));
+                        current = Some(first_child);
+                        parent = Some(index);
+                        prev = None;
                     }
-                    Some(index)
                 }
-                _ => None,
-            }
-        }
-
-        fn append(
-            document: &mut Document,
-            data: node::Data,
-            parent: Option<usize>,
-            prev: Option<usize>,
-        ) -> usize {
-            let index = document.nodes.len();
-
-            document.nodes.push(node::Raw {
-                index,
-                parent,
-                prev,
-                next: None,
-                first_child: None,
-                last_child: None,
-                data,
+            }
+            match stack.pop() {
+                Some(state) => (current, parent, prev) = state,
+                None => break,
+            }
+        }
+
+        if self.source_spans {
+            document.source = Some(Source {
+                text: StrTendril::new(),
+                spans,
             });
+        }
+        document
+    }
+
+    fn parse_error(&mut self, message: Cow<'static, str>) {
+        self.errors.push(HtmlError {
+            line: self.line,
+            message,
+        });
+    }
+
+    fn set_current_line(&mut self, line: u64) {
+        self.line = line;
+    }
+
+    fn get_document(&mut self) -> usize {
+        0
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a usize) -> ExpandedName<'a> {
+        match self.nodes[*target].data {
+            Some(node::Data::Element(ref name, _)) => name.expanded(),
+            _ => panic!("not an element"),
+        }
+    }
+
+    fn create_element(
+        &mut self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags,
+    ) -> usize {
+        // Elements implied by another token are empty, where that token starts.
+        let implied = !self
+            .start_tag
+            .as_ref()
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(&name.local));
+        let attrs = attrs
+            .into_iter()
+            .map(|attr| (attr.name, attr.value))
+            .collect();
+        let element = self.create(Some(node::Data::Element(name, attrs)));
+        if let Some(ref mut span) = self.nodes[element].span {
+            if implied {
+                span.end = span.start;
+            }
+        }
+        if flags.mathml_annotation_xml_integration_point {
+            self.integration_points.insert(element);
+        }
+        element
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> usize {
+        self.create(Some(node::Data::Comment(text)))
+    }
+
+    fn create_pi(&mut self, target: StrTendril, data: StrTendril) -> usize {
+        self.create(Some(node::Data::ProcessingInstruction { target, data }))
+    }
+
+    fn append(&mut self, parent: &usize, child: NodeOrText<usize>) {
+        let child = match child {
+            NodeOrText::AppendNode(child) => child,
+            NodeOrText::AppendText(text) => {
+                if self.append_text(self.nodes[*parent].last_child, &text) {
+                    return;
+                }
+                self.create_text(text)
+            }
+        };
+        self.append_child(*parent, child);
+    }
+
+    fn append_based_on_parent_node(
+        &mut self,
+        element: &usize,
+        prev_element: &usize,
+        child: NodeOrText<usize>,
+    ) {
+        if self.nodes[*element].parent.is_some() {
+            self.append_before_sibling(element, child);
+        } else {
+            self.append(prev_element, child);
+        }
+    }
+
+    fn append_doctype_to_document(
+        &mut self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    ) {
+        let doctype = self.create(Some(node::Data::Doctype {
+            name,
+            public_id,
+            system_id,
+        }));
+        self.append_child(0, doctype);
+    }
+
+    // Template contents are kept as the children of the template element.
+    fn get_template_contents(&mut self, target: &usize) -> usize {
+        *target
+    }
+
+    fn same_node(&self, x: &usize, y: &usize) -> bool {
+        x == y
+    }
 
-            if let Some(parent) = parent {
-                let parent = &mut document.nodes[parent];
-                if parent.first_child.is_none() {
-                    parent.first_child = Some(index);
+    fn set_quirks_mode(&mut self, _: QuirksMode) {}
+
+    fn append_before_sibling(&mut self, sibling: &usize, child: NodeOrText<usize>) {
+        // Content foster-parented out of a table is still within it in the
+        // input, before its end tag.
+        if let Some(node::Data::Element(ref name, _)) = self.nodes[*sibling].data {
+            if name.ns == ns!(html) && name.local == local_name!("table") {
+                let end = match child {
+                    NodeOrText::AppendNode(_) => self.token,
+                    NodeOrText::AppendText(_) => self.text,
+                };
+                if let (Some(span), Some(end)) = (self.nodes[*sibling].span.as_mut(), end) {
+                    span.end = span.end.max(end.end);
                 }
-                parent.last_child = Some(index);
             }
+        }
 
-            if let Some(prev) = prev {
-                document.nodes[prev].next = Some(index);
+        let child = match child {
+            NodeOrText::AppendNode(child) => {
+                self.detach(child);
+                child
+            }
+            NodeOrText::AppendText(text) => {
+                if self.append_text(self.nodes[*sibling].prev, &text) {
+                    return;
+                }
+                self.create_text(text)
             }
+        };
+        self.insert_before(*sibling, child);
+    }
 
-            index
+    fn add_attrs_if_missing(&mut self, target: &usize, attrs: Vec<Attribute>) {
+        if let Some(node::Data::Element(_, ref mut existing)) = self.nodes[*target].data {
+            for attr in attrs {
+                if !existing.iter().any(|(name, _)| *name == attr.name) {
+                    existing.push((attr.name, attr.value));
+                }
+            }
         }
     }
+
+    fn remove_from_parent(&mut self, target: &usize) {
+        self.detach(*target);
+    }
+
+    fn reparent_children(&mut self, node: &usize, new_parent: &usize) {
+        while let Some(child) = self.nodes[*node].first_child {
+            self.detach(child);
+            self.append_child(*new_parent, child);
+        }
+    }
+
+    fn is_mathml_annotation_xml_integration_point(&self, handle: &usize) -> bool {
+        self.integration_points.contains(*handle)
+    }
+}
+
+fn append(
+    document: &mut Document,
+    data: node::Data,
+    parent: Option<usize>,
+    prev: Option<usize>,
+) -> usize {
+    let index = document.nodes.len();
+
+    document.nodes.push(node::Raw {
+        index,
+        parent,
+        prev,
+        next: None,
+        first_child: None,
+        last_child: None,
+        data,
+    });
+
+    if let Some(parent) = parent {
+        let parent = &mut document.nodes[parent];
+        if parent.first_child.is_none() {
+            parent.first_child = Some(index);
+        }
+        parent.last_child = Some(index);
+    }
+
+    if let Some(prev) = prev {
+        document.nodes[prev].next = Some(index);
+    }
+
+    index
 }
 
+#[allow(clippy::needless_lifetimes)]
 impl<'a> From<&'a str> for Document {
     /// Parses the given `&str` into a `Document`.
     fn from(str: &str) -> Document {
@@ -146,6 +1009,7 @@ pub struct Find<'a, P> {
     document: &'a Document,
     next: usize,
     predicate: P,
+    in_templates: bool,
 }
 
 impl<'a, P: Predicate> std::fmt::Debug for Find<'a, P> {
@@ -153,6 +1017,7 @@ impl<'a, P: Predicate> std::fmt::Debug for Find<'a, P> {
         f.debug_struct("Find")
             .field("document", &self.document)
             .field("next", &self.next)
+            .field("in_templates", &self.in_templates)
             // predicate may be closure not implementing Debug
             .finish()
     }
@@ -170,7 +1035,11 @@ impl<'a, P: Predicate> Iterator for Find<'a, P> {
     fn next(&mut self) -> Option<Node<'a>> {
         while self.next < self.document.nodes.len() {
             let node = self.document.nth(self.next).unwrap();
-            self.next += 1;
+            self.next = if !self.in_templates && node.hides_contents() {
+                node.descendants_end()
+            } else {
+                self.next + 1
+            };
             if self.predicate.matches(&node) {
                 return Some(node);
             }
@@ -0,0 +1,241 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+/// The number of bytes searched for a `<meta>` charset declaration.
+pub(crate) const PRESCAN_LENGTH: usize = 1024;
+
+/// Determine the character encoding of an HTML byte stream.
+///
+/// In order of precedence, this uses a byte order mark, the `transport` charset
+/// label (e.g. from a `Content-Type` header), a `<meta charset>` or
+/// `<meta http-equiv>` declaration in the first 1024 bytes, and finally falls
+/// back to UTF-8 if the first 1024 bytes are valid UTF-8 (ignoring a truncated
+/// character at the end) and windows-1252 otherwise. Only looking at the first
+/// 1024 bytes means `DocumentBuilder` can decide the encoding before the whole
+/// input has arrived, and agrees with `Document::from_read`.
+pub fn sniff(bytes: &[u8], transport: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    if let Some(encoding) = transport.and_then(|label| Encoding::for_label(label.as_bytes())) {
+        return encoding;
+    }
+
+    let bytes = &bytes[..bytes.len().min(PRESCAN_LENGTH)];
+    if let Some(encoding) = prescan(bytes) {
+        return encoding;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(error) if error.error_len().is_none() => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let rest = &bytes[position..];
+        if rest.starts_with(b"<!--") {
+            position += 2 + find(&rest[2..], b"-->").map_or(rest.len(), |end| end + 3);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|&b| is_whitespace(b) || b == b'/')
+        {
+            position += 5;
+            if let Some(encoding) = meta(bytes, &mut position) {
+                return Some(encoding);
+            }
+        } else if rest.len() > 2
+            && rest[0] == b'<'
+            && (rest[1].is_ascii_alphabetic() || rest[1] == b'/' && rest[2].is_ascii_alphabetic())
+        {
+            position += 1;
+            while position < bytes.len()
+                && !is_whitespace(bytes[position])
+                && bytes[position] != b'>'
+            {
+                position += 1;
+            }
+            while attribute(bytes, &mut position).is_some() {}
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            position += find(rest, b">").map_or(rest.len(), |end| end + 1);
+        } else {
+            position += 1;
+        }
+    }
+
+    None
+}
+
+// Process the attributes of a `<meta>` tag, returning the encoding it declares.
+fn meta(bytes: &[u8], position: &mut usize) -> Option<&'static Encoding> {
+    let mut seen = Vec::new();
+    let mut got_pragma = false;
+    let mut need_pragma = None;
+    let mut charset = None;
+
+    while let Some((name, value)) = attribute(bytes, position) {
+        if seen.contains(&name) {
+            continue;
+        }
+        match &*name {
+            b"http-equiv" => got_pragma |= value.eq_ignore_ascii_case(b"content-type"),
+            b"content" if charset.is_none() => {
+                if let Some(encoding) = content_charset(&value) {
+                    charset = Some(encoding);
+                    need_pragma = Some(true);
+                }
+            }
+            b"charset" if charset.is_none() => {
+                charset = Encoding::for_label(&value);
+                need_pragma = Some(false);
+            }
+            _ => {}
+        }
+        seen.push(name);
+    }
+
+    match need_pragma {
+        None => return None,
+        Some(true) if !got_pragma => return None,
+        _ => {}
+    }
+
+    charset.map(|encoding| {
+        if encoding == UTF_16BE || encoding == UTF_16LE {
+            UTF_8
+        } else if encoding == X_USER_DEFINED {
+            WINDOWS_1252
+        } else {
+            encoding
+        }
+    })
+}
+
+// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+fn content_charset(content: &[u8]) -> Option<&'static Encoding> {
+    let mut position = 0;
+    loop {
+        position += find_ignore_case(&content[position..], b"charset")? + 7;
+        while content.get(position).copied().is_some_and(is_whitespace) {
+            position += 1;
+        }
+        if content.get(position) == Some(&b'=') {
+            position += 1;
+            break;
+        }
+    }
+    while content.get(position).copied().is_some_and(is_whitespace) {
+        position += 1;
+    }
+
+    let value = match content.get(position) {
+        Some(&quote) if quote == b'"' || quote == b'\'' => {
+            let rest = &content[position + 1..];
+            &rest[..rest.iter().position(|&b| b == quote)?]
+        }
+        Some(_) => {
+            let rest = &content[position..];
+            let end = rest
+                .iter()
+                .position(|&b| is_whitespace(b) || b == b';')
+                .unwrap_or(rest.len());
+            &rest[..end]
+        }
+        None => return None,
+    };
+
+    Encoding::for_label(value)
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#concept-get-attributes-when-sniffing
+fn attribute(bytes: &[u8], position: &mut usize) -> Option<(Vec<u8>, Vec<u8>)> {
+    let byte = |position: usize| bytes.get(position).copied();
+
+    while byte(*position).is_some_and(|b| is_whitespace(b) || b == b'/') {
+        *position += 1;
+    }
+    if let None | Some(b'>') = byte(*position) {
+        return None;
+    }
+
+    let mut name = Vec::new();
+    let mut value = Vec::new();
+
+    loop {
+        match byte(*position) {
+            None => return Some((name, value)),
+            Some(b'=') if !name.is_empty() => {
+                *position += 1;
+                break;
+            }
+            Some(b) if is_whitespace(b) => {
+                while byte(*position).is_some_and(is_whitespace) {
+                    *position += 1;
+                }
+                if byte(*position) != Some(b'=') {
+                    return Some((name, value));
+                }
+                *position += 1;
+                break;
+            }
+            Some(b'/') | Some(b'>') => return Some((name, value)),
+            Some(b) => {
+                name.push(b.to_ascii_lowercase());
+                *position += 1;
+            }
+        }
+    }
+
+    while byte(*position).is_some_and(is_whitespace) {
+        *position += 1;
+    }
+
+    match byte(*position) {
+        Some(quote) if quote == b'"' || quote == b'\'' => {
+            *position += 1;
+            while let Some(b) = byte(*position) {
+                *position += 1;
+                if b == quote {
+                    break;
+                }
+                value.push(b.to_ascii_lowercase());
+            }
+        }
+        Some(b'>') | None => {}
+        Some(_) => {
+            while let Some(b) = byte(*position) {
+                if is_whitespace(b) || b == b'>' {
+                    break;
+                }
+                value.push(b.to_ascii_lowercase());
+                *position += 1;
+            }
+        }
+    }
+
+    Some((name, value))
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0C')
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn find_ignore_case(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
@@ -1,5 +1,7 @@
 #![warn(missing_debug_implementations)]
 pub mod document;
+pub mod encoding;
 pub mod node;
 pub mod predicate;
 pub mod selection;
+pub mod selector;
@@ -1,11 +1,12 @@
 use std::{fmt, io};
 
 use html5ever::tendril::StrTendril;
-use html5ever::{serialize, QualName};
+use html5ever::{local_name, namespace_url, ns, serialize, QualName};
 
 use crate::document::Document;
 use crate::predicate::Predicate;
 use crate::selection::Selection;
+use crate::selector::{ParseError, Selector};
 
 /// The Node type specific data stored by every Node.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -13,6 +14,15 @@ pub enum Data {
     Text(StrTendril),
     Element(QualName, Vec<(QualName, StrTendril)>),
     Comment(StrTendril),
+    Doctype {
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    },
+    ProcessingInstruction {
+        target: StrTendril,
+        data: StrTendril,
+    },
 }
 
 /// Internal representation of a Node. Not of much use without a reference to a
@@ -28,7 +38,22 @@ pub struct Raw {
     pub data: Data,
 }
 
-/// A single node of an HTML document. Nodes may be HTML elements, comments, or text nodes.
+/// The location of a Node in the input it was parsed from. Offsets are into
+/// the parsed text, i.e. after decoding for documents read from bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// The 1-based line on which the Node starts.
+    pub line: u64,
+    /// The 1-based column, in characters, at which the Node starts.
+    pub column: usize,
+    /// The byte offset at which the Node starts.
+    pub start: usize,
+    /// The byte offset just past the end of the Node, including its end tag.
+    pub end: usize,
+}
+
+/// A single node of an HTML document. Nodes may be HTML elements, comments,
+/// text nodes, doctypes, or processing instructions.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Node<'a> {
     document: &'a Document,
@@ -55,11 +80,27 @@ impl<'a> Node<'a> {
         &self.document.nodes[self.index]
     }
 
-    /// Get the text node, HTML element, or comment from a Node.
+    /// Get the text node, HTML element, comment, doctype, or processing
+    /// instruction from a Node.
     pub fn data(&self) -> &'a Data {
         &self.raw().data
     }
 
+    /// Get the location of this Node in the input it was parsed from, or None
+    /// if it is not known. Spans are only recorded for documents parsed with
+    /// `ParseOpts::source_spans` set.
+    pub fn source_span(&self) -> Option<Span> {
+        self.document.span(self.index)
+    }
+
+    /// Get the markup this Node was parsed from, exactly as it appeared in the
+    /// input, or None if it is not known. Unlike `html`, this does not
+    /// re-serialize the Node, so the original formatting is preserved.
+    pub fn source_html(&self) -> Option<&'a str> {
+        let span = self.source_span()?;
+        self.document.source()?.get(span.start..span.end)
+    }
+
     /// Get the name of a Node if it is an HTML element, or None otherwise.
     pub fn name(&self) -> Option<&'a str> {
         match *self.data() {
@@ -69,6 +110,7 @@ impl<'a> Node<'a> {
     }
 
     /// Get the value of the attribute `name` from a Node representing a HTML element.
+    #[allow(clippy::needless_borrowed_reference)]
     pub fn attr(&self, name: &str) -> Option<&'a str> {
         match *self.data() {
             Data::Element(_, ref attrs) => attrs
@@ -79,6 +121,28 @@ impl<'a> Node<'a> {
         }
     }
 
+    /// Get the namespace URI of a Node representing an element, e.g.
+    /// `"http://www.w3.org/2000/svg"` for inline SVG.
+    pub fn namespace(&self) -> Option<&'a str> {
+        match *self.data() {
+            Data::Element(ref name, _) => Some(&name.ns),
+            _ => None,
+        }
+    }
+
+    /// Get the value of the attribute `name` in namespace `ns` from a Node
+    /// representing an element. Attributes without a namespace, like most HTML
+    /// attributes, are in the empty namespace `""`.
+    pub fn attr_ns(&self, ns: &str, name: &str) -> Option<&'a str> {
+        match *self.data() {
+            Data::Element(_, ref attrs) => attrs
+                .iter()
+                .find(|(name_, _)| ns == &name_.ns && name == &name_.local)
+                .map(|(_, value)| value.as_ref()),
+            _ => None,
+        }
+    }
+
     /// Get an iterator over the names and values of attributes of the Element.
     /// Returns an empty iterator for non Element nodes.
     pub fn attrs(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
@@ -120,19 +184,24 @@ impl<'a> Node<'a> {
             .map(|index| self.document.nth(index).unwrap())
     }
 
-    /// Get the combined textual content of a Node and all of its children.
+    /// Get the combined textual content of a Node and all of its children,
+    /// skipping the contents of `<template>` elements like browsers do.
     pub fn text(&self) -> String {
         let mut string = String::new();
-        recur(self, &mut string);
-        return string;
+        self.push_text(&mut string);
+        string
+    }
 
-        fn recur(node: &Node, string: &mut String) {
-            if let Some(text) = node.as_text() {
-                string.push_str(text);
-            }
-            for child in node.children() {
-                recur(&child, string)
-            }
+    // Append the text of this Node and its descendants to `string`.
+    pub(crate) fn push_text(&self, string: &mut String) {
+        if let Some(text) = self.as_text() {
+            string.push_str(text);
+        }
+        if self.hides_contents() {
+            return;
+        }
+        for child in self.children() {
+            child.push_text(string)
         }
     }
 
@@ -143,6 +212,14 @@ impl<'a> Node<'a> {
         String::from_utf8(buf).unwrap()
     }
 
+    /// Serialize a Node to an XML string. Unlike `html`, this keeps namespace
+    /// prefixes and declares the namespaces used.
+    pub fn xml(&self) -> String {
+        let mut buf = Vec::new();
+        xml5ever::serialize::serialize(&mut buf, self, Default::default()).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
     /// Serialize a Node's children to an HTML string.
     pub fn inner_html(&self) -> String {
         let mut buf = Vec::new();
@@ -152,15 +229,32 @@ impl<'a> Node<'a> {
         String::from_utf8(buf).unwrap()
     }
 
-    /// Search for Nodes fulfilling `predicate` in the descendants of a Node.
+    /// Search for Nodes fulfilling `predicate` in the descendants of a Node,
+    /// skipping the contents of `<template>` elements.
     pub fn find<P: Predicate>(&self, predicate: P) -> Find<'a, P> {
         Find {
             document: self.document,
-            descendants: self.descendants(),
+            next: self.index + 1,
+            end: self.descendants_end(),
             predicate,
+            in_templates: false,
         }
     }
 
+    /// Like `find`, but also searches the contents of `<template>` elements.
+    pub fn find_in_templates<P: Predicate>(&self, predicate: P) -> Find<'a, P> {
+        Find {
+            in_templates: true,
+            ..self.find(predicate)
+        }
+    }
+
+    /// Search for Nodes matching the CSS selector `selector` in the
+    /// descendants of a Node.
+    pub fn select(&self, selector: &str) -> Result<Find<'a, Selector>, ParseError> {
+        Ok(self.find(Selector::parse(selector)?))
+    }
+
     /// Evaluate a predicate on this Node.
     pub fn is<P: Predicate>(&self, p: P) -> bool {
         p.matches(self)
@@ -182,6 +276,39 @@ impl<'a> Node<'a> {
         }
     }
 
+    /// Get the contents of this Node if it is a `<template>` element, or None
+    /// otherwise. Template contents are stored as the children of the element.
+    /// Like in browsers, `find`, `text`, and predicates looking at descendants
+    /// or text such as `Has`, `ContainsText` and `Empty` skip them, but
+    /// `children`, `descendants` and predicates looking at ancestors such as
+    /// `Descendant` do not.
+    pub fn template_contents(&self) -> Option<Children<'a>> {
+        match *self.data() {
+            Data::Element(ref name, _)
+                if name.ns == ns!(html) && name.local == local_name!("template") =>
+            {
+                Some(self.children())
+            }
+            _ => None,
+        }
+    }
+
+    // One past the index of the last descendant of this Node. Nodes are stored
+    // in document order, so its descendants are the Nodes in between.
+    pub(crate) fn descendants_end(&self) -> usize {
+        let mut last = *self;
+        while let Some(last_child) = last.last_child() {
+            last = last_child;
+        }
+        last.index() + 1
+    }
+
+    // Whether `find` and the text of this Node skip over its contents, unless
+    // searching in templates.
+    pub(crate) fn hides_contents(&self) -> bool {
+        self.template_contents().is_some()
+    }
+
     /// Construct an iterator over a Node's child Nodes.
     pub fn children(&self) -> Children<'a> {
         Children {
@@ -205,6 +332,7 @@ impl<'a> fmt::Debug for Node<'a> {
         struct Attrs<'a>(&'a [(QualName, StrTendril)]);
 
         impl<'a> fmt::Debug for Attrs<'a> {
+            #[allow(clippy::needless_borrowed_reference)]
             fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
                 self.0
                     .iter()
@@ -233,11 +361,30 @@ impl<'a> fmt::Debug for Node<'a> {
                 .field("children", &Children(self))
                 .finish(),
             Data::Comment(ref comment) => f.debug_tuple("Comment").field(&&**comment).finish(),
+            Data::Doctype {
+                ref name,
+                ref public_id,
+                ref system_id,
+            } => f
+                .debug_struct("Doctype")
+                .field("name", &&**name)
+                .field("public_id", &&**public_id)
+                .field("system_id", &&**system_id)
+                .finish(),
+            Data::ProcessingInstruction {
+                ref target,
+                ref data,
+            } => f
+                .debug_struct("ProcessingInstruction")
+                .field("target", &&**target)
+                .field("data", &&**data)
+                .finish(),
         }
     }
 }
 
 impl<'a> serialize::Serialize for Node<'a> {
+    #[allow(clippy::needless_borrowed_reference)]
     fn serialize<S: serialize::Serializer>(
         &self,
         serializer: &mut S,
@@ -259,10 +406,43 @@ impl<'a> serialize::Serialize for Node<'a> {
                 Ok(())
             }
             Data::Comment(ref comment) => serializer.write_comment(comment),
+            Data::Doctype {
+                ref name,
+                ref public_id,
+                ref system_id,
+            } => {
+                // The serializer only writes a name, so append the identifiers.
+                let mut doctype = String::from(&**name);
+                if !public_id.is_empty() {
+                    doctype.push_str(" PUBLIC ");
+                    push_quoted(&mut doctype, public_id);
+                    if !system_id.is_empty() {
+                        doctype.push(' ');
+                        push_quoted(&mut doctype, system_id);
+                    }
+                } else if !system_id.is_empty() {
+                    doctype.push_str(" SYSTEM ");
+                    push_quoted(&mut doctype, system_id);
+                }
+                serializer.write_doctype(&doctype)
+            }
+            Data::ProcessingInstruction {
+                ref target,
+                ref data,
+            } => serializer.write_processing_instruction(target, data),
         }
     }
 }
 
+// Append a doctype identifier in double quotes, or in single quotes if it
+// contains a double quote.
+fn push_quoted(string: &mut String, id: &str) {
+    let quote = if id.contains('"') { '\'' } else { '"' };
+    string.push(quote);
+    string.push_str(id);
+    string.push(quote);
+}
+
 #[derive(Clone, Debug)]
 pub struct Descendants<'a> {
     start: Node<'a>,
@@ -315,15 +495,19 @@ impl<'a> Iterator for Descendants<'a> {
 
 pub struct Find<'a, P: Predicate> {
     document: &'a Document,
-    descendants: Descendants<'a>,
+    next: usize,
+    end: usize,
     predicate: P,
+    in_templates: bool,
 }
 
 impl<'a, P: Predicate> std::fmt::Debug for Find<'a, P> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Find")
             .field("document", &self.document)
-            .field("descendants", &self.descendants)
+            .field("next", &self.next)
+            .field("end", &self.end)
+            .field("in_templates", &self.in_templates)
             // predicate may be closure not implementing Debug
             .finish()
     }
@@ -339,11 +523,22 @@ impl<'a, P: Predicate> Iterator for Find<'a, P> {
     type Item = Node<'a>;
 
     fn next(&mut self) -> Option<Node<'a>> {
-        self.descendants.find(|&node| self.predicate.matches(&node))
+        while self.next < self.end {
+            let node = self.document.nth(self.next).unwrap();
+            self.next = if !self.in_templates && node.hides_contents() {
+                node.descendants_end()
+            } else {
+                self.next + 1
+            };
+            if self.predicate.matches(&node) {
+                return Some(node);
+            }
+        }
+        None
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Children<'a> {
     document: &'a Document,
     next: Option<Node<'a>>,
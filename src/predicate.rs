@@ -1,8 +1,31 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use html5ever::QualName;
+
 use crate::node::{self, Node};
 
+#[cfg(feature = "regex")]
+use regex::Regex;
+
 /// A trait implemented by all `Node` matchers.
 pub trait Predicate {
     fn matches(&self, node: &Node) -> bool;
+    /// Write a CSS-like description of this Predicate. Predicates which can't
+    /// be described, like closures, write a placeholder such as `<fn>`.
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<predicate>")
+    }
+    /// Display this Predicate as CSS-like text, e.g. `#hmenus a` for
+    /// `Attr("id", "hmenus").descendant(Name("a"))`. Trait objects implement
+    /// `Display` directly.
+    fn describe(&self) -> Describe<'_, Self>
+    where
+        Self: Sized,
+    {
+        Describe(self)
+    }
     fn or<T: Predicate>(self, other: T) -> Or<Self, T>
     where
         Self: Sized,
@@ -33,6 +56,30 @@ pub trait Predicate {
     {
         Descendant(self, other)
     }
+    fn adjacent_sibling<T: Predicate>(self, other: T) -> AdjacentSibling<Self, T>
+    where
+        Self: Sized,
+    {
+        AdjacentSibling(self, other)
+    }
+    fn general_sibling<T: Predicate>(self, other: T) -> GeneralSibling<Self, T>
+    where
+        Self: Sized,
+    {
+        GeneralSibling(self, other)
+    }
+    fn has<T: Predicate>(self, other: T) -> And<Self, Has<T>>
+    where
+        Self: Sized,
+    {
+        And(self, Has(other))
+    }
+    fn boxed(self) -> BoxedPredicate
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        Box::new(self)
+    }
 }
 
 /// Matches any Node.
@@ -43,15 +90,23 @@ impl Predicate for Any {
     fn matches(&self, _: &Node) -> bool {
         true
     }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":any-node")
+    }
 }
 
 /// Matches Element Node with name `T`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Name<T>(pub T);
 
-impl<'a> Predicate for Name<&'a str> {
+impl<T: AsRef<str>> Predicate for Name<T> {
     fn matches(&self, node: &Node) -> bool {
-        node.name() == Some(self.0)
+        node.name() == Some(self.0.as_ref())
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_ident(f, self.0.as_ref())
     }
 }
 
@@ -59,12 +114,20 @@ impl<'a> Predicate for Name<&'a str> {
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Class<T>(pub T);
 
-impl<'a> Predicate for Class<&'a str> {
+impl<T: AsRef<str>> Predicate for Class<T> {
+    #[allow(clippy::unnecessary_map_or)]
     fn matches(&self, node: &Node) -> bool {
         node.attr("class").map_or(false, |classes| {
-            classes.split_whitespace().any(|class| class == self.0)
+            classes
+                .split_whitespace()
+                .any(|class| class == self.0.as_ref())
         })
     }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(".")?;
+        write_ident(f, self.0.as_ref())
+    }
 }
 
 /// Matches if the Predicate `T` does not match.
@@ -75,113 +138,1425 @@ impl<T: Predicate> Predicate for Not<T> {
     fn matches(&self, node: &Node) -> bool {
         !self.0.matches(node)
     }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ":not({})", self.0.describe())
+    }
 }
 
 /// Matches Element Node containing attribute `N` with value `V` if `V` is an
-/// `&str`, or any value if `V` is `()`.
+/// `AttrValue`, or any value if `V` is `()`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Attr<N, V>(pub N, pub V);
 
-impl<'a> Predicate for Attr<&'a str, &'a str> {
+impl<N: AsRef<str>, V: AttrValue> Predicate for Attr<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        node.attr(self.0) == Some(self.1)
+        attr_matches(node, self.0.as_ref(), Operator::Equals, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::Equals, &self.1)
     }
 }
 
-impl<'a> Predicate for Attr<&'a str, ()> {
+impl<N: AsRef<str>> Predicate for Attr<N, ()> {
     fn matches(&self, node: &Node) -> bool {
-        node.attr(self.0).is_some()
+        node.attr(self.0.as_ref()).is_some()
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+        write_ident(f, self.0.as_ref())?;
+        f.write_str("]")
     }
 }
 
-/// Matches if the function returns true.
-impl<F: Fn(&Node) -> bool> Predicate for F {
+/// Matches Element Node with namespace URI `N` and local name `L`, e.g.
+/// `NsName("http://www.w3.org/2000/svg", "title")`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NsName<N, L>(pub N, pub L);
+
+impl<N: AsRef<str>, L: AsRef<str>> Predicate for NsName<N, L> {
     fn matches(&self, node: &Node) -> bool {
-        self(node)
+        node.namespace() == Some(self.0.as_ref()) && node.name() == Some(self.1.as_ref())
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{{}}}", self.0.as_ref())?;
+        write_ident(f, self.1.as_ref())
     }
 }
 
-/// Matches any Element Node.
+/// Matches Element Node containing attribute `L` in namespace `N` with value
+/// `V` if `V` is an `AttrValue`, or any value if `V` is `()`, e.g.
+/// `NsAttr("http://www.w3.org/1999/xlink", "href", ())`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Element;
+pub struct NsAttr<N, L, V>(pub N, pub L, pub V);
 
-impl Predicate for Element {
+impl<N: AsRef<str>, L: AsRef<str>, V: AttrValue> Predicate for NsAttr<N, L, V> {
     fn matches(&self, node: &Node) -> bool {
-        matches!(*node.data(), node::Data::Element(..))
+        node.attr_ns(self.0.as_ref(), self.1.as_ref())
+            .is_some_and(|actual| {
+                Operator::Equals.matches(actual, self.2.as_str(), self.2.ignore_case())
+            })
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(
+            f,
+            self.0.as_ref(),
+            self.1.as_ref(),
+            Operator::Equals,
+            &self.2,
+        )
     }
 }
 
-/// Matches any Text Node.
+impl<N: AsRef<str>, L: AsRef<str>> Predicate for NsAttr<N, L, ()> {
+    fn matches(&self, node: &Node) -> bool {
+        node.attr_ns(self.0.as_ref(), self.1.as_ref()).is_some()
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{{{}}}", self.0.as_ref())?;
+        write_ident(f, self.1.as_ref())?;
+        f.write_str("]")
+    }
+}
+
+/// Matches Element Node containing attribute `N` with a value starting with
+/// `V`, like the CSS selector `[N^=V]`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Text;
+pub struct AttrPrefix<N, V>(pub N, pub V);
 
-impl Predicate for Text {
+impl<N: AsRef<str>, V: AttrValue> Predicate for AttrPrefix<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        matches!(*node.data(), node::Data::Text(..))
+        attr_matches(node, self.0.as_ref(), Operator::Prefix, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::Prefix, &self.1)
     }
 }
 
-/// Matches any Comment Node.
+/// Matches Element Node containing attribute `N` with a value ending with `V`,
+/// like the CSS selector `[N$=V]`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Comment;
+pub struct AttrSuffix<N, V>(pub N, pub V);
 
-impl Predicate for Comment {
+impl<N: AsRef<str>, V: AttrValue> Predicate for AttrSuffix<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        matches!(*node.data(), node::Data::Comment(..))
+        attr_matches(node, self.0.as_ref(), Operator::Suffix, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::Suffix, &self.1)
     }
 }
 
-/// Matches if either inner Predicate `A` or `B` matches the Node.
+/// Matches Element Node containing attribute `N` with a value containing `V`,
+/// like the CSS selector `[N*=V]`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Or<A, B>(pub A, pub B);
+pub struct AttrSubstring<N, V>(pub N, pub V);
 
-impl<A: Predicate, B: Predicate> Predicate for Or<A, B> {
+impl<N: AsRef<str>, V: AttrValue> Predicate for AttrSubstring<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        self.0.matches(node) || self.1.matches(node)
+        attr_matches(node, self.0.as_ref(), Operator::Substring, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::Substring, &self.1)
     }
 }
 
-/// Matches if the inner Predicate `A` and `B` both match the Node.
+/// Matches Element Node containing attribute `N` with a whitespace-separated
+/// list of words one of which is `V`, like the CSS selector `[N~=V]`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct And<A, B>(pub A, pub B);
+pub struct AttrWord<N, V>(pub N, pub V);
 
-impl<A: Predicate, B: Predicate> Predicate for And<A, B> {
+impl<N: AsRef<str>, V: AttrValue> Predicate for AttrWord<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        self.0.matches(node) && self.1.matches(node)
+        attr_matches(node, self.0.as_ref(), Operator::Includes, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::Includes, &self.1)
     }
 }
 
-/// Matches if inner Predicate `B` matches the node and `A` matches the parent
-/// of the node.
+/// Matches Element Node containing attribute `N` with value `V` or starting
+/// with `V` followed by `-`, like the CSS selector `[N|=V]`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Child<A, B>(pub A, pub B);
+pub struct AttrDash<N, V>(pub N, pub V);
 
-impl<A: Predicate, B: Predicate> Predicate for Child<A, B> {
+impl<N: AsRef<str>, V: AttrValue> Predicate for AttrDash<N, V> {
     fn matches(&self, node: &Node) -> bool {
-        if let Some(parent) = node.parent() {
-            self.1.matches(node) && self.0.matches(&parent)
-        } else {
-            false
+        attr_matches(node, self.0.as_ref(), Operator::DashMatch, &self.1)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_attr(f, "", self.0.as_ref(), Operator::DashMatch, &self.1)
+    }
+}
+
+/// A value the attribute predicates compare attribute values against. A `&str`
+/// is compared case-sensitively, like with the CSS `s` attribute flag.
+pub trait AttrValue {
+    /// Get the value to compare against.
+    fn as_str(&self) -> &str;
+
+    /// Whether to compare ignoring ASCII case.
+    fn ignore_case(&self) -> bool {
+        false
+    }
+}
+
+impl AttrValue for &str {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl AttrValue for String {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl AttrValue for Cow<'_, str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl AttrValue for Arc<str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+/// Makes an attribute predicate compare value `T` ignoring ASCII case, like
+/// the CSS `i` attribute flag, e.g. `Attr("type", IgnoreCase("checkbox"))`.
+/// Text predicates ignore the case of all characters instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IgnoreCase<T>(pub T);
+
+impl<T: AttrValue> AttrValue for IgnoreCase<T> {
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    fn ignore_case(&self) -> bool {
+        true
+    }
+}
+
+impl<T: TextValue> TextValue for IgnoreCase<T> {
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    fn ignore_case(&self) -> bool {
+        true
+    }
+
+    fn normalize_whitespace(&self) -> bool {
+        self.0.normalize_whitespace()
+    }
+}
+
+fn attr_matches<V: AttrValue>(node: &Node, name: &str, operator: Operator, value: &V) -> bool {
+    node.attr(name)
+        .is_some_and(|actual| operator.matches(actual, value.as_str(), value.ignore_case()))
+}
+
+/// A CSS attribute selector operator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Operator {
+    Equals,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+impl Operator {
+    /// The CSS syntax of this operator, e.g. `^=`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Operator::Equals => "=",
+            Operator::Includes => "~=",
+            Operator::DashMatch => "|=",
+            Operator::Prefix => "^=",
+            Operator::Suffix => "$=",
+            Operator::Substring => "*=",
+        }
+    }
+
+    /// Whether attribute value `value` matches `expected` under this operator.
+    pub(crate) fn matches(self, value: &str, expected: &str, ignore_case: bool) -> bool {
+        let eq = |a: &[u8], b: &[u8]| {
+            if ignore_case {
+                a.eq_ignore_ascii_case(b)
+            } else {
+                a == b
+            }
+        };
+        let (value, expected) = (value.as_bytes(), expected.as_bytes());
+        let len = expected.len();
+
+        match self {
+            Operator::Equals => eq(value, expected),
+            Operator::Includes => {
+                !expected.is_empty()
+                    && !expected.iter().any(|&b| is_whitespace(b))
+                    && value
+                        .split(|&b| is_whitespace(b))
+                        .any(|word| eq(word, expected))
+            }
+            Operator::DashMatch => {
+                eq(value, expected)
+                    || value.len() > len && eq(&value[..len], expected) && value[len] == b'-'
+            }
+            Operator::Prefix => {
+                !expected.is_empty() && value.len() >= len && eq(&value[..len], expected)
+            }
+            Operator::Suffix => {
+                !expected.is_empty()
+                    && value.len() >= len
+                    && eq(&value[value.len() - len..], expected)
+            }
+            Operator::Substring => {
+                !expected.is_empty() && value.windows(len).any(|window| eq(window, expected))
+            }
         }
     }
 }
 
-/// Matches if inner Predicate `B` matches the node and `A` matches any of the
-/// parents of node.
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0C')
+}
+
+/// Matches Element Node containing attribute `N` with a value matching the
+/// regular expression. Requires the `regex` feature.
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct AttrRegex<N>(pub N, pub Regex);
+
+#[cfg(feature = "regex")]
+impl<N: AsRef<str>> Predicate for AttrRegex<N> {
+    fn matches(&self, node: &Node) -> bool {
+        node.attr(self.0.as_ref())
+            .is_some_and(|value| self.1.is_match(value))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+        write_ident(f, self.0.as_ref())?;
+        f.write_str("=~")?;
+        write_string(f, self.1.as_str())?;
+        f.write_str("]")
+    }
+}
+
+/// Matches Text Node with text matching the regular expression. Requires the
+/// `regex` feature.
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct TextRegex(pub Regex);
+
+#[cfg(feature = "regex")]
+impl Predicate for TextRegex {
+    fn matches(&self, node: &Node) -> bool {
+        node.as_text().is_some_and(|text| self.0.is_match(text))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":matches(")?;
+        write_string(f, self.0.as_str())?;
+        f.write_str(")")
+    }
+}
+
+/// Matches Element Node with a name matching the regular expression. Requires
+/// the `regex` feature.
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct NameRegex(pub Regex);
+
+#[cfg(feature = "regex")]
+impl Predicate for NameRegex {
+    fn matches(&self, node: &Node) -> bool {
+        node.name().is_some_and(|name| self.0.is_match(name))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":name-matches(")?;
+        write_string(f, self.0.as_str())?;
+        f.write_str(")")
+    }
+}
+
+/// Matches Node whose text, as returned by `Node::text`, contains `T`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Descendant<A, B>(pub A, pub B);
+pub struct ContainsText<T>(pub T);
 
-impl<A: Predicate, B: Predicate> Predicate for Descendant<A, B> {
+impl<T: TextValue> Predicate for ContainsText<T> {
     fn matches(&self, node: &Node) -> bool {
-        if self.1.matches(node) {
-            let mut node = *node;
-            while let Some(parent) = node.parent() {
-                if self.0.matches(&parent) {
-                    return true;
+        let text = text(node);
+        contains(
+            Normalize::new(text, &self.0),
+            Normalize::new(Some(self.0.as_str()), &self.0),
+        )
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_text(f, "contains", &self.0)
+    }
+}
+
+/// Matches Node whose own text contains `T`: the text of a Text Node, or the
+/// text of the Text Node children of an Element Node, ignoring descendants.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OwnTextContains<T>(pub T);
+
+impl<T: TextValue> Predicate for OwnTextContains<T> {
+    fn matches(&self, node: &Node) -> bool {
+        let children = if node.hides_contents() {
+            None
+        } else {
+            Some(node.children())
+        };
+        let text = node.as_text().into_iter().chain(
+            children
+                .into_iter()
+                .flatten()
+                .filter_map(|node| node.as_text()),
+        );
+        contains(
+            Normalize::new(text, &self.0),
+            Normalize::new(Some(self.0.as_str()), &self.0),
+        )
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_text(f, "own-text-contains", &self.0)
+    }
+}
+
+/// Matches Node whose text, as returned by `Node::text`, is `T`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextEquals<T>(pub T);
+
+impl<T: TextValue> Predicate for TextEquals<T> {
+    fn matches(&self, node: &Node) -> bool {
+        let text = text(node);
+        Normalize::new(text, &self.0).eq(Normalize::new(Some(self.0.as_str()), &self.0))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_text(f, "text-equals", &self.0)
+    }
+}
+
+// The text of `node`, in the pieces `Node::text` combines.
+fn text<'a>(node: &Node<'a>) -> impl Iterator<Item = &'a str> {
+    let descendants = if node.hides_contents() {
+        None
+    } else {
+        Some(node.find(Text))
+    };
+    node.as_text().into_iter().chain(
+        descendants
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node.as_text()),
+    )
+}
+
+/// A value the text predicates compare text against. A `&str` is compared
+/// exactly.
+pub trait TextValue {
+    /// Get the value to compare against.
+    fn as_str(&self) -> &str;
+
+    /// Whether to compare ignoring case.
+    fn ignore_case(&self) -> bool {
+        false
+    }
+
+    /// Whether to collapse whitespace before comparing.
+    fn normalize_whitespace(&self) -> bool {
+        false
+    }
+}
+
+impl TextValue for &str {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl TextValue for String {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl TextValue for Cow<'_, str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+impl TextValue for Arc<str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+/// Makes a text predicate collapse every run of whitespace into a single space
+/// and ignore leading and trailing whitespace, in both the text and value `T`,
+/// e.g. `TextEquals(NormalizeWhitespace("Price:"))`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NormalizeWhitespace<T>(pub T);
+
+impl<T: TextValue> TextValue for NormalizeWhitespace<T> {
+    fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    fn ignore_case(&self) -> bool {
+        self.0.ignore_case()
+    }
+
+    fn normalize_whitespace(&self) -> bool {
+        true
+    }
+}
+
+// The characters of a sequence of strings, lowercased and with whitespace
+// normalized as requested, produced without allocating.
+#[derive(Clone)]
+struct Normalize<'a, I> {
+    strs: I,
+    chars: std::str::Chars<'a>,
+    lowercase: Option<std::char::ToLowercase>,
+    held: Option<char>,
+    space: bool,
+    started: bool,
+    ignore_case: bool,
+    normalize_whitespace: bool,
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Normalize<'a, I> {
+    fn new<S, T>(strs: S, value: &T) -> Normalize<'a, I>
+    where
+        S: IntoIterator<Item = &'a str, IntoIter = I>,
+        T: TextValue,
+    {
+        Normalize {
+            strs: strs.into_iter(),
+            chars: "".chars(),
+            lowercase: None,
+            held: None,
+            space: false,
+            started: false,
+            ignore_case: value.ignore_case(),
+            normalize_whitespace: value.normalize_whitespace(),
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        loop {
+            match self.chars.next() {
+                Some(c) => return Some(c),
+                None => self.chars = self.strs.next()?.chars(),
+            }
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Iterator for Normalize<'a, I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.lowercase.as_mut().and_then(Iterator::next) {
+            return Some(c);
+        }
+
+        let c = match self.held.take() {
+            Some(c) => c,
+            None => loop {
+                let c = self.next_char()?;
+                if self.normalize_whitespace && c.is_whitespace() {
+                    // Only emit a space if more text follows.
+                    self.space = self.started;
+                } else {
+                    break c;
                 }
-                node = parent;
+            },
+        };
+        if self.space {
+            self.space = false;
+            self.held = Some(c);
+            return Some(' ');
+        }
+
+        self.started = true;
+        if self.ignore_case {
+            let mut lowercase = c.to_lowercase();
+            let c = lowercase.next();
+            self.lowercase = Some(lowercase);
+            c
+        } else {
+            Some(c)
+        }
+    }
+}
+
+// Needles up to this many chars are searched for without allocating.
+const INLINE_NEEDLE: usize = 32;
+
+// Whether `needle` occurs in `haystack`, using the Knuth-Morris-Pratt
+// algorithm to read `haystack` once without buffering it.
+fn contains<H, N>(haystack: H, needle: N) -> bool
+where
+    H: Iterator<Item = char>,
+    N: Iterator<Item = char>,
+{
+    let mut inline = ['\0'; INLINE_NEEDLE];
+    let mut spilled = Vec::new();
+    let mut length = 0;
+    for c in needle {
+        if length < INLINE_NEEDLE {
+            inline[length] = c;
+        } else {
+            if spilled.is_empty() {
+                spilled.extend_from_slice(&inline);
             }
+            spilled.push(c);
+        }
+        length += 1;
+    }
+    if length == 0 {
+        return true;
+    }
+
+    let mut inline_fallback = [0; INLINE_NEEDLE];
+    let mut spilled_fallback;
+    let (needle, fallback) = if length <= INLINE_NEEDLE {
+        (&inline[..length], &mut inline_fallback[..length])
+    } else {
+        spilled_fallback = vec![0; length];
+        (&spilled[..], &mut spilled_fallback[..])
+    };
+
+    // The length of the longest proper prefix of `needle[..=i]` which is also
+    // a suffix of it, for each `i`.
+    let mut matched = 0;
+    for i in 1..needle.len() {
+        while matched > 0 && needle[i] != needle[matched] {
+            matched = fallback[matched - 1];
+        }
+        if needle[i] == needle[matched] {
+            matched += 1;
+        }
+        fallback[i] = matched;
+    }
+
+    let mut matched = 0;
+    for c in haystack {
+        while matched > 0 && c != needle[matched] {
+            matched = fallback[matched - 1];
+        }
+        if c == needle[matched] {
+            matched += 1;
+            if matched == needle.len() {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// A type-erased `Predicate`, e.g. for building a set of rules at runtime.
+pub type BoxedPredicate = Box<dyn Predicate + Send + Sync>;
+
+impl Predicate for BoxedPredicate {
+    fn matches(&self, node: &Node) -> bool {
+        (**self).matches(node)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).describe_to(f)
+    }
+}
+
+/// Matches if the function returns true.
+impl<F: Fn(&Node) -> bool> Predicate for F {
+    fn matches(&self, node: &Node) -> bool {
+        self(node)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<fn>")
+    }
+}
+
+/// Matches any Element Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Element;
+
+impl Predicate for Element {
+    fn matches(&self, node: &Node) -> bool {
+        matches!(*node.data(), node::Data::Element(..))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("*")
+    }
+}
+
+/// Matches any Text Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Text;
+
+impl Predicate for Text {
+    fn matches(&self, node: &Node) -> bool {
+        matches!(*node.data(), node::Data::Text(..))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":text")
+    }
+}
+
+/// Matches any Comment Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Comment;
+
+impl Predicate for Comment {
+    fn matches(&self, node: &Node) -> bool {
+        matches!(*node.data(), node::Data::Comment(..))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":comment")
+    }
+}
+
+/// Matches any Doctype Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Doctype;
+
+impl Predicate for Doctype {
+    fn matches(&self, node: &Node) -> bool {
+        matches!(*node.data(), node::Data::Doctype { .. })
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":doctype")
+    }
+}
+
+/// Matches any Processing Instruction Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProcessingInstruction;
+
+impl Predicate for ProcessingInstruction {
+    fn matches(&self, node: &Node) -> bool {
+        matches!(*node.data(), node::Data::ProcessingInstruction { .. })
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":processing-instruction")
+    }
+}
+
+/// Matches Element Node which is the `a`n+`b`th element among its siblings
+/// for some n >= 0, counting from 1, like the CSS `:nth-child(an+b)`
+/// pseudo-class. `NthChild(0, 3)` matches the 3rd element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NthChild(pub i32, pub i32);
+
+impl Predicate for NthChild {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some() && nth_matches(self.0, self.1, position(node, false, None))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_nth(f, "nth-child", self.0, self.1)
+    }
+}
+
+/// Like `NthChild`, but counting from the last sibling, like the CSS
+/// `:nth-last-child(an+b)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NthLastChild(pub i32, pub i32);
+
+impl Predicate for NthLastChild {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some() && nth_matches(self.0, self.1, position(node, true, None))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_nth(f, "nth-last-child", self.0, self.1)
+    }
+}
+
+/// Like `NthChild`, but only counting siblings with the same name, like the
+/// CSS `:nth-of-type(an+b)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NthOfType(pub i32, pub i32);
+
+impl Predicate for NthOfType {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node)
+            .is_some_and(|name| nth_matches(self.0, self.1, position(node, false, Some(name))))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_nth(f, "nth-of-type", self.0, self.1)
+    }
+}
+
+/// Like `NthOfType`, but counting from the last sibling, like the CSS
+/// `:nth-last-of-type(an+b)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NthLastOfType(pub i32, pub i32);
+
+impl Predicate for NthLastOfType {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node)
+            .is_some_and(|name| nth_matches(self.0, self.1, position(node, true, Some(name))))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_nth(f, "nth-last-of-type", self.0, self.1)
+    }
+}
+
+/// Matches Element Node which is the first element among its siblings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FirstChild;
+
+impl Predicate for FirstChild {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some() && position(node, false, None) == 1
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":first-child")
+    }
+}
+
+/// Matches Element Node which is the last element among its siblings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LastChild;
+
+impl Predicate for LastChild {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some() && position(node, true, None) == 1
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":last-child")
+    }
+}
+
+/// Matches Element Node without element siblings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OnlyChild;
+
+impl Predicate for OnlyChild {
+    fn matches(&self, node: &Node) -> bool {
+        FirstChild.matches(node) && LastChild.matches(node)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":only-child")
+    }
+}
+
+/// Matches Element Node without element or text children.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Empty;
+
+impl Predicate for Empty {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some()
+            && (node.hides_contents()
+                || node
+                    .children()
+                    .all(|child| !Element.matches(&child) && !Text.matches(&child)))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":empty")
+    }
+}
+
+/// Matches Element Node without a parent, such as the `html` element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Root;
+
+impl Predicate for Root {
+    fn matches(&self, node: &Node) -> bool {
+        element_name(node).is_some() && node.parent().is_none()
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(":root")
+    }
+}
+
+fn element_name<'a>(node: &Node<'a>) -> Option<&'a QualName> {
+    match *node.data() {
+        node::Data::Element(ref name, _) => Some(name),
+        _ => None,
+    }
+}
+
+// The 1-based position of `node` among its element siblings, counting from the
+// end if `last` is set and only counting elements named `of_type` if given.
+pub(crate) fn position<'a>(node: &Node<'a>, last: bool, of_type: Option<&QualName>) -> i64 {
+    let step = |node: &Node<'a>| if last { node.next() } else { node.prev() };
+    let mut position = 1;
+    let mut current = step(node);
+    while let Some(sibling) = current {
+        if let node::Data::Element(ref name, _) = *sibling.data() {
+            let counted = match of_type {
+                Some(of_type) => of_type.ns == name.ns && of_type.local == name.local,
+                None => true,
+            };
+            if counted {
+                position += 1;
+            }
+        }
+        current = step(&sibling);
+    }
+    position
+}
+
+// Whether `position` is `a * n + b` for some non-negative integer `n`.
+pub(crate) fn nth_matches(a: i32, b: i32, position: i64) -> bool {
+    let (a, b) = (i64::from(a), i64::from(b));
+    if a == 0 {
+        position == b
+    } else {
+        let difference = position - b;
+        difference % a == 0 && difference / a >= 0
+    }
+}
+
+/// Matches if either inner Predicate `A` or `B` matches the Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for Or<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.matches(node) || self.1.matches(node)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.0.describe(), self.1.describe())
+    }
+}
+
+/// Matches if the inner Predicate `A` and `B` both match the Node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for And<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.matches(node) && self.1.matches(node)
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_compound(f, [&self.0 as &dyn Predicate, &self.1])
+    }
+}
+
+/// Matches if any Predicate in the collection `T` matches the Node, checking
+/// them in order and stopping at the first match. Implemented for `Vec<P>`,
+/// `&[P]` and `[P; N]`, and can be collected from an iterator of predicates.
+/// An empty collection never matches.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AnyOf<T>(pub T);
+
+impl<P: Predicate> Predicate for AnyOf<Vec<P>> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().any(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_list(f, &self.0)
+    }
+}
+
+impl<P: Predicate> Predicate for AnyOf<&[P]> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().any(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_list(f, self.0)
+    }
+}
+
+impl<P: Predicate, const N: usize> Predicate for AnyOf<[P; N]> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().any(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_list(f, &self.0)
+    }
+}
+
+impl<P: Predicate> FromIterator<P> for AnyOf<Vec<P>> {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        AnyOf(iter.into_iter().collect())
+    }
+}
+
+/// Matches if every Predicate in the collection `T` matches the Node, checking
+/// them in order and stopping at the first mismatch. Implemented for `Vec<P>`,
+/// `&[P]` and `[P; N]`, and can be collected from an iterator of predicates.
+/// An empty collection always matches.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllOf<T>(pub T);
+
+impl<P: Predicate> Predicate for AllOf<Vec<P>> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().all(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_compound(f, self.0.iter().map(|p| p as &dyn Predicate))
+    }
+}
+
+impl<P: Predicate> Predicate for AllOf<&[P]> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().all(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_compound(f, self.0.iter().map(|p| p as &dyn Predicate))
+    }
+}
+
+impl<P: Predicate, const N: usize> Predicate for AllOf<[P; N]> {
+    fn matches(&self, node: &Node) -> bool {
+        self.0.iter().all(|predicate| predicate.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_compound(f, self.0.iter().map(|p| p as &dyn Predicate))
+    }
+}
+
+impl<P: Predicate> FromIterator<P> for AllOf<Vec<P>> {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        AllOf(iter.into_iter().collect())
+    }
+}
+
+/// Matches if inner Predicate `B` matches the node and `A` matches the parent
+/// of the node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Child<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for Child<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        if let Some(parent) = node.parent() {
+            self.1.matches(node) && self.0.matches(&parent)
+        } else {
+            false
+        }
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_complex(f, &self.0, ">", &self.1)
+    }
+}
+
+/// Matches if inner Predicate `B` matches the node and `A` matches any of the
+/// parents of node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Descendant<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for Descendant<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        if self.1.matches(node) {
+            let mut node = *node;
+            while let Some(parent) = node.parent() {
+                if self.0.matches(&parent) {
+                    return true;
+                }
+                node = parent;
+            }
+        }
+        false
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_complex(f, &self.0, "", &self.1)
+    }
+}
+
+/// Matches if inner Predicate `B` matches the node and `A` matches the element
+/// immediately preceding it, like the CSS `A + B` combinator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AdjacentSibling<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for AdjacentSibling<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        self.1.matches(node) && prev_element(node).is_some_and(|prev| self.0.matches(&prev))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_complex(f, &self.0, "+", &self.1)
+    }
+}
+
+/// Matches if inner Predicate `B` matches the node and `A` matches any of the
+/// elements preceding it, like the CSS `A ~ B` combinator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GeneralSibling<A, B>(pub A, pub B);
+
+impl<A: Predicate, B: Predicate> Predicate for GeneralSibling<A, B> {
+    fn matches(&self, node: &Node) -> bool {
+        if self.1.matches(node) {
+            let mut current = prev_element(node);
+            while let Some(prev) = current {
+                if self.0.matches(&prev) {
+                    return true;
+                }
+                current = prev_element(&prev);
+            }
+        }
+        false
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_complex(f, &self.0, "~", &self.1)
+    }
+}
+
+/// Matches if inner Predicate `P` matches any of the descendants of the node,
+/// like the CSS `:has(P)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Has<P>(pub P);
+
+impl<P: Predicate> Predicate for Has<P> {
+    fn matches(&self, node: &Node) -> bool {
+        node.find(|node: &Node| self.0.matches(node))
+            .next()
+            .is_some()
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ":has({})", self.0.describe())
+    }
+}
+
+/// Matches if inner Predicate `P` matches any of the children of the node,
+/// like the CSS `:has(> P)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HasChild<P>(pub P);
+
+impl<P: Predicate> Predicate for HasChild<P> {
+    fn matches(&self, node: &Node) -> bool {
+        !node.hides_contents() && node.children().any(|child| self.0.matches(&child))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_relative(f, ">", &self.0)
+    }
+}
+
+/// Matches if inner Predicate `P` matches the element immediately following
+/// the node, like the CSS `:has(+ P)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HasAdjacentSibling<P>(pub P);
+
+impl<P: Predicate> Predicate for HasAdjacentSibling<P> {
+    fn matches(&self, node: &Node) -> bool {
+        next_element(node).is_some_and(|next| self.0.matches(&next))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_relative(f, "+", &self.0)
+    }
+}
+
+/// Matches if inner Predicate `P` matches any of the elements following the
+/// node, like the CSS `:has(~ P)` pseudo-class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HasGeneralSibling<P>(pub P);
+
+impl<P: Predicate> Predicate for HasGeneralSibling<P> {
+    fn matches(&self, node: &Node) -> bool {
+        let mut current = next_element(node);
+        while let Some(next) = current {
+            if self.0.matches(&next) {
+                return true;
+            }
+            current = next_element(&next);
+        }
+        false
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_relative(f, "~", &self.0)
+    }
+}
+
+// The closest preceding sibling of `node` which is an element.
+pub(crate) fn prev_element<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut current = node.prev();
+    while let Some(prev) = current {
+        if let node::Data::Element(..) = *prev.data() {
+            return Some(prev);
+        }
+        current = prev.prev();
+    }
+    None
+}
+
+// The closest following sibling of `node` which is an element.
+fn next_element<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut current = node.next();
+    while let Some(next) = current {
+        if let node::Data::Element(..) = *next.data() {
+            return Some(next);
+        }
+        current = next.next();
+    }
+    None
+}
+
+/// Displays a `Predicate` as CSS-like text. Returned by `Predicate::describe`.
+pub struct Describe<'a, P: ?Sized>(&'a P);
+
+impl<P: Predicate + ?Sized> fmt::Display for Describe<'_, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.describe_to(f)
+    }
+}
+
+impl<P: Predicate + ?Sized> fmt::Debug for Describe<'_, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.describe_to(f)
+    }
+}
+
+impl fmt::Display for dyn Predicate + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.describe_to(f)
+    }
+}
+
+impl fmt::Display for dyn Predicate + Send + Sync + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.describe_to(f)
+    }
+}
+
+// Write `predicate`, wrapped in `:is()` if its description contains any of
+// `separators` outside of strings and brackets.
+fn write_part(
+    f: &mut fmt::Formatter,
+    predicate: &dyn Predicate,
+    separators: &[char],
+) -> fmt::Result {
+    let description = predicate.to_string();
+    if has_top_level(&description, separators) {
+        write!(f, ":is({})", description)
+    } else {
+        f.write_str(&description)
+    }
+}
+
+fn has_top_level(description: &str, separators: &[char]) -> bool {
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut chars = description.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            _ if quoted => {}
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ if depth == 0 && separators.contains(&c) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// Write predicates which must all match as a compound selector like `a.b`,
+// wrapping parts which aren't simple selectors, or type selectors after the
+// first part, in `:is()`.
+fn write_compound<'a>(
+    f: &mut fmt::Formatter,
+    predicates: impl IntoIterator<Item = &'a dyn Predicate>,
+) -> fmt::Result {
+    let mut empty = true;
+    for predicate in predicates {
+        let description = predicate.to_string();
+        let is_type = !description.starts_with(['.', '#', '[', ':']);
+        if has_top_level(&description, &[',', ' ']) || !empty && is_type {
+            write!(f, ":is({})", description)?;
+        } else {
+            f.write_str(&description)?;
+        }
+        empty = false;
+    }
+    if empty {
+        f.write_str(":any-node")?;
+    }
+    Ok(())
+}
+
+// Write predicates of which any must match as a selector list like `a, b`.
+fn write_list<P: Predicate>(f: &mut fmt::Formatter, predicates: &[P]) -> fmt::Result {
+    if predicates.is_empty() {
+        return f.write_str(":is()");
+    }
+    for (index, predicate) in predicates.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        predicate.describe_to(f)?;
+    }
+    Ok(())
+}
+
+// Write a complex selector like `a > b`, or `a b` if `combinator` is empty.
+fn write_complex(
+    f: &mut fmt::Formatter,
+    a: &dyn Predicate,
+    combinator: &str,
+    b: &dyn Predicate,
+) -> fmt::Result {
+    write_part(f, a, &[','])?;
+    if combinator.is_empty() {
+        f.write_str(" ")?;
+    } else {
+        write!(f, " {} ", combinator)?;
+    }
+    write_part(f, b, &[',', ' '])
+}
+
+// Write a `:has()` pseudo-class with a relative selector like `> a`.
+fn write_relative(
+    f: &mut fmt::Formatter,
+    combinator: &str,
+    predicate: &dyn Predicate,
+) -> fmt::Result {
+    write!(f, ":has({} ", combinator)?;
+    write_part(f, predicate, &[',', ' '])?;
+    f.write_str(")")
+}
+
+fn write_attr<V: AttrValue>(
+    f: &mut fmt::Formatter,
+    ns: &str,
+    name: &str,
+    operator: Operator,
+    value: &V,
+) -> fmt::Result {
+    let value_ = value.as_str();
+    if ns.is_empty()
+        && name == "id"
+        && operator == Operator::Equals
+        && !value.ignore_case()
+        && is_ident(value_)
+    {
+        f.write_str("#")?;
+        return write_ident(f, value_);
+    }
+    f.write_str("[")?;
+    if !ns.is_empty() {
+        write!(f, "{{{}}}", ns)?;
+    }
+    write_ident(f, name)?;
+    f.write_str(operator.as_str())?;
+    write_string(f, value_)?;
+    if value.ignore_case() {
+        f.write_str(" i")?;
+    }
+    f.write_str("]")
+}
+
+fn write_text<T: TextValue>(f: &mut fmt::Formatter, name: &str, value: &T) -> fmt::Result {
+    write!(f, ":{}(", name)?;
+    if value.normalize_whitespace() {
+        f.write_str("normalize-space(")?;
+        write_string(f, value.as_str())?;
+        f.write_str(")")?;
+    } else {
+        write_string(f, value.as_str())?;
+    }
+    if value.ignore_case() {
+        f.write_str(" i")?;
+    }
+    f.write_str(")")
+}
+
+fn write_nth(f: &mut fmt::Formatter, name: &str, a: i32, b: i32) -> fmt::Result {
+    write!(f, ":{}(", name)?;
+    match a {
+        0 => write!(f, "{}", b)?,
+        1 => f.write_str("n")?,
+        -1 => f.write_str("-n")?,
+        _ => write!(f, "{}n", a)?,
+    }
+    if a != 0 && b != 0 {
+        write!(f, "{:+}", b)?;
+    }
+    f.write_str(")")
+}
+
+fn is_ident(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '-')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+// Write `ident` as a CSS identifier, escaping characters as needed.
+fn write_ident(f: &mut fmt::Formatter, ident: &str) -> fmt::Result {
+    for (index, c) in ident.chars().enumerate() {
+        if index == 0 && c.is_ascii_digit() {
+            write!(f, "\\{:x} ", u32::from(c))?;
+        } else if c.is_alphanumeric() || c == '_' || c == '-' || !c.is_ascii() {
+            write!(f, "{}", c)?;
+        } else {
+            write!(f, "\\{}", c)?;
+        }
+    }
+    Ok(())
+}
+
+// Write `string` as a double quoted CSS string.
+fn write_string(f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in string.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{}", c)?,
+            '\n' => f.write_str("\\a ")?,
+            _ => write!(f, "{}", c)?,
         }
-        false
     }
+    f.write_str("\"")
 }
@@ -1,6 +1,11 @@
+use std::iter::Rev;
+use std::ops::{BitAnd, BitOr, BitXor, Bound, RangeBounds, Sub};
+
+use html5ever::serialize;
+
 use crate::document::Document;
 use crate::node::Node;
-use crate::predicate::Predicate;
+use crate::predicate::{Has, Predicate};
 use bit_set::{self, BitSet};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -18,9 +23,16 @@ impl<'a> Selection<'a> {
         Iter {
             selection: self,
             inner: self.bit_set.iter(),
+            back: self.bit_set.get_ref().len(),
+            remaining: self.bit_set.len(),
         }
     }
 
+    /// Iterate over the nodes in reverse document order.
+    pub fn rev<'sel>(&'sel self) -> Rev<Iter<'sel, 'a>> {
+        self.iter().rev()
+    }
+
     pub fn filter<P: Predicate>(&self, p: P) -> Selection<'a> {
         Selection {
             document: self.document,
@@ -34,28 +46,42 @@ impl<'a> Selection<'a> {
 
     pub fn find<P: Predicate>(&self, p: P) -> Selection<'a> {
         let mut bit_set = BitSet::new();
+        // The last searched node and the end of its descendants. Nodes are
+        // stored in document order, so the selected nodes in between are
+        // already covered, unless they are in skipped template contents.
+        let (mut start, mut end) = (0, 0);
 
         for node in self {
-            recur(&node, &mut bit_set);
+            if node.index() < end && !in_hidden_contents(&node, start) {
+                continue;
+            }
+            if node.index() >= end {
+                start = node.index();
+                end = node.descendants_end();
+            }
+            for found in node.find(|node: &Node| p.matches(node)) {
+                bit_set.insert(found.index());
+            }
         }
 
         return Selection {
             document: self.document,
-            bit_set: bit_set
-                .iter()
-                .filter(|&index| p.matches(&self.document.nth(index).unwrap()))
-                .collect(),
+            bit_set,
         };
 
-        fn recur(node: &Node, bit_set: &mut BitSet) {
-            if bit_set.contains(node.index()) {
-                return;
-            }
-
-            for child in node.children() {
-                recur(&child, bit_set);
-                bit_set.insert(child.index());
+        // Whether an ancestor of `node` below `start` hides its contents.
+        fn in_hidden_contents(node: &Node, start: usize) -> bool {
+            let mut current = node.parent();
+            while let Some(parent) = current {
+                if parent.index() <= start {
+                    return false;
+                }
+                if parent.hides_contents() {
+                    return true;
+                }
+                current = parent.parent();
             }
+            false
         }
     }
 
@@ -118,6 +144,100 @@ impl<'a> Selection<'a> {
         }
     }
 
+    /// Get the first node matching `p` of each node and its ancestors.
+    pub fn closest<P: Predicate>(&self, p: P) -> Selection<'a> {
+        let mut bit_set = BitSet::new();
+        for node in self {
+            let mut current = Some(node);
+            while let Some(node) = current {
+                if p.matches(&node) {
+                    bit_set.insert(node.index());
+                    break;
+                }
+                current = node.parent();
+            }
+        }
+
+        Selection {
+            document: self.document,
+            bit_set,
+        }
+    }
+
+    /// Get the ancestors of each node up to, but not including, the first one
+    /// matching `p`.
+    pub fn parents_until<P: Predicate>(&self, p: P) -> Selection<'a> {
+        self.walk(Node::parent, |node| p.matches(node))
+    }
+
+    /// Get all the siblings following each node.
+    pub fn next_all(&self) -> Selection<'a> {
+        self.walk(Node::next, |_| false)
+    }
+
+    /// Get all the siblings preceding each node.
+    pub fn prev_all(&self) -> Selection<'a> {
+        self.walk(Node::prev, |_| false)
+    }
+
+    /// Get the siblings following each node up to, but not including, the
+    /// first one matching `p`.
+    pub fn next_until<P: Predicate>(&self, p: P) -> Selection<'a> {
+        self.walk(Node::next, |node| p.matches(node))
+    }
+
+    /// Get the siblings preceding each node up to, but not including, the
+    /// first one matching `p`.
+    pub fn prev_until<P: Predicate>(&self, p: P) -> Selection<'a> {
+        self.walk(Node::prev, |node| p.matches(node))
+    }
+
+    /// Get the siblings of each node, excluding the node itself. Nodes without
+    /// a parent, like the top level nodes of a fragment, can have siblings too.
+    pub fn siblings(&self) -> Selection<'a> {
+        self.prev_all().union(&self.next_all())
+    }
+
+    /// Get the nodes not matching `p`.
+    pub fn not<P: Predicate>(&self, p: P) -> Selection<'a> {
+        self.filter(|node: &Node| !p.matches(node))
+    }
+
+    /// Get the nodes with a descendant matching `p`.
+    pub fn has<P: Predicate>(&self, p: P) -> Selection<'a> {
+        self.filter(Has(p))
+    }
+
+    /// Check if any of the nodes match `p`.
+    pub fn is<P: Predicate>(&self, p: P) -> bool {
+        self.iter().any(|node| p.matches(&node))
+    }
+
+    // Collect the nodes reached from each node by repeatedly calling `step`,
+    // stopping before the first one matching `until`.
+    fn walk<S, U>(&self, step: S, until: U) -> Selection<'a>
+    where
+        S: Fn(&Node<'a>) -> Option<Node<'a>>,
+        U: Fn(&Node<'a>) -> bool,
+    {
+        let mut bit_set = BitSet::new();
+        for node in self {
+            let mut current = step(&node);
+            while let Some(node) = current {
+                // The rest of the walk was already collected from an earlier node.
+                if until(&node) || !bit_set.insert(node.index()) {
+                    break;
+                }
+                current = step(&node);
+            }
+        }
+
+        Selection {
+            document: self.document,
+            bit_set,
+        }
+    }
+
     pub fn first(&self) -> Option<Node<'a>> {
         self.bit_set
             .iter()
@@ -126,10 +246,78 @@ impl<'a> Selection<'a> {
     }
 
     pub fn last(&self) -> Option<Node<'a>> {
-        self.bit_set
-            .iter()
-            .last()
-            .map(|index| self.document.nth(index).unwrap())
+        self.rev().next()
+    }
+
+    /// Get the `index`th node, counting from 0.
+    pub fn nth(&self, index: usize) -> Option<Node<'a>> {
+        self.iter().nth(index)
+    }
+
+    /// Get the `index`th node, counting from 0, or from the end if `index` is
+    /// negative, so `at(-1)` is the last node.
+    pub fn at(&self, index: isize) -> Option<Node<'a>> {
+        if index < 0 {
+            self.rev().nth(index.unsigned_abs() - 1)
+        } else {
+            self.nth(index.unsigned_abs())
+        }
+    }
+
+    /// Get the nodes at the positions in `range`, counting from 0. Positions
+    /// past the end are ignored.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Selection<'a> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        };
+
+        Selection {
+            document: self.document,
+            bit_set: self
+                .bit_set
+                .iter()
+                .skip(start)
+                .take(end.saturating_sub(start))
+                .collect(),
+        }
+    }
+
+    /// Get the text of each node, as returned by `Node::text`.
+    pub fn texts(&self) -> Vec<String> {
+        self.iter().map(|node| node.text()).collect()
+    }
+
+    /// Get the value of the attribute `name` of each node which has it.
+    pub fn attrs(&self, name: &str) -> Vec<&'a str> {
+        self.iter().filter_map(|node| node.attr(name)).collect()
+    }
+
+    /// Get the text of all the nodes, joined with `separator`.
+    pub fn text(&self, separator: &str) -> String {
+        let mut string = String::new();
+        for (index, node) in self.iter().enumerate() {
+            if index > 0 {
+                string.push_str(separator);
+            }
+            node.push_text(&mut string);
+        }
+        string
+    }
+
+    /// Serialize all the nodes to a single HTML string.
+    pub fn html(&self) -> String {
+        let mut buf = Vec::new();
+        for node in self {
+            serialize::serialize(&mut buf, &node, Default::default()).unwrap();
+        }
+        String::from_utf8(buf).unwrap()
     }
 
     pub fn len(&self) -> usize {
@@ -139,12 +327,79 @@ impl<'a> Selection<'a> {
     pub fn is_empty(&self) -> bool {
         self.bit_set.is_empty()
     }
+
+    /// Get the nodes in either this or the `other` Selection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selections are from different documents, as are the other
+    /// set operations below.
+    pub fn union(&self, other: &Selection<'a>) -> Selection<'a> {
+        self.combine(other, BitSet::union_with)
+    }
+
+    /// Get the nodes in both this and the `other` Selection.
+    pub fn intersection(&self, other: &Selection<'a>) -> Selection<'a> {
+        self.combine(other, BitSet::intersect_with)
+    }
+
+    /// Get the nodes in this Selection but not in the `other` one.
+    pub fn difference(&self, other: &Selection<'a>) -> Selection<'a> {
+        self.combine(other, BitSet::difference_with)
+    }
+
+    /// Get the nodes in exactly one of this and the `other` Selection.
+    pub fn symmetric_difference(&self, other: &Selection<'a>) -> Selection<'a> {
+        self.combine(other, BitSet::symmetric_difference_with)
+    }
+
+    fn combine(&self, other: &Selection<'a>, op: fn(&mut BitSet, &BitSet)) -> Selection<'a> {
+        assert!(
+            std::ptr::eq(self.document, other.document),
+            "cannot combine selections from different documents"
+        );
+        let mut bit_set = self.bit_set.clone();
+        op(&mut bit_set, &other.bit_set);
+        Selection {
+            document: self.document,
+            bit_set,
+        }
+    }
+}
+
+macro_rules! impl_op {
+    ($trait:ident, $fn:ident, $method:ident) => {
+        impl<'a> $trait<&Selection<'a>> for &Selection<'a> {
+            type Output = Selection<'a>;
+
+            fn $fn(self, other: &Selection<'a>) -> Selection<'a> {
+                self.$method(other)
+            }
+        }
+
+        impl<'a> $trait for Selection<'a> {
+            type Output = Selection<'a>;
+
+            fn $fn(self, other: Selection<'a>) -> Selection<'a> {
+                self.$method(&other)
+            }
+        }
+    };
 }
 
+impl_op!(BitOr, bitor, union);
+impl_op!(BitAnd, bitand, intersection);
+impl_op!(Sub, sub, difference);
+impl_op!(BitXor, bitxor, symmetric_difference);
+
 #[derive(Clone)]
 pub struct Iter<'sel, 'doc: 'sel> {
     selection: &'sel Selection<'doc>,
     inner: bit_set::Iter<'sel, u32>,
+    // One past the last index not yet returned from the back.
+    back: usize,
+    // The number of nodes not yet returned from either end.
+    remaining: usize,
 }
 
 impl<'sel, 'doc: 'sel> std::fmt::Debug for Iter<'sel, 'doc> {
@@ -161,12 +416,36 @@ impl<'sel, 'doc> Iterator for Iter<'sel, 'doc> {
     type Item = Node<'doc>;
 
     fn next(&mut self) -> Option<Node<'doc>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
         self.inner
             .next()
             .map(|index| self.selection.document.nth(index).unwrap())
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
+impl<'sel, 'doc> DoubleEndedIterator for Iter<'sel, 'doc> {
+    fn next_back(&mut self) -> Option<Node<'doc>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.back -= 1;
+        while !self.selection.bit_set.contains(self.back) {
+            self.back -= 1;
+        }
+        Some(self.selection.document.nth(self.back).unwrap())
+    }
+}
+
+impl<'sel, 'doc> ExactSizeIterator for Iter<'sel, 'doc> {}
+
 impl<'sel, 'doc> IntoIterator for &'sel Selection<'doc> {
     type Item = Node<'doc>;
     type IntoIter = Iter<'sel, 'doc>;
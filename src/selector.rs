@@ -0,0 +1,808 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use html5ever::{namespace_url, ns, QualName};
+
+use crate::node::{Data, Node};
+use crate::predicate::{nth_matches, position, prev_element, Operator, Predicate};
+
+/// A CSS selector compiled from a Selectors Level 3 string, usable anywhere a
+/// `Predicate` is. The `:has()` pseudo-class from Selectors Level 4 is also
+/// supported.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Selector {
+    source: String,
+    complexes: Vec<Complex>,
+}
+
+impl Selector {
+    /// Parses a comma separated list of CSS selectors.
+    pub fn parse(selector: &str) -> Result<Selector, ParseError> {
+        let mut parser = Parser {
+            input: selector,
+            position: 0,
+            in_has: false,
+        };
+        let complexes = parser.parse_list()?;
+        if let Some(c) = parser.peek() {
+            return Err(parser.error(format!("unexpected character {:?}", c)));
+        }
+        Ok(Selector {
+            source: selector.into(),
+            complexes,
+        })
+    }
+
+    /// Get the string this Selector was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for Selector {
+    type Err = ParseError;
+
+    fn from_str(selector: &str) -> Result<Selector, ParseError> {
+        Selector::parse(selector)
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Selector").field(&self.source).finish()
+    }
+}
+
+impl Predicate for Selector {
+    fn matches(&self, node: &Node) -> bool {
+        self.complexes.iter().any(|complex| complex.matches(node))
+    }
+
+    fn describe_to(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// An error encountered while parsing a `Selector`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    column: usize,
+    message: String,
+}
+
+impl ParseError {
+    /// Get the 1-based column, counted in characters, of the offending input.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Get a description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+/// A sequence of compound selectors separated by combinators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Complex {
+    compounds: Vec<Vec<Simple>>,
+    combinators: Vec<Combinator>,
+}
+
+impl Complex {
+    fn matches(&self, node: &Node) -> bool {
+        self.matches_at(self.compounds.len() - 1, node, None)
+    }
+
+    // Whether an element related to `anchor` by `combinator` matches, like
+    // `anchor:has(<combinator> <self>)` does.
+    fn matches_relative(&self, combinator: Combinator, anchor: &Node) -> bool {
+        let matches = |node: &Node| {
+            self.matches_at(self.compounds.len() - 1, node, Some((combinator, anchor)))
+        };
+        match combinator {
+            Combinator::Descendant | Combinator::Child => anchor.find(&matches).next().is_some(),
+            Combinator::NextSibling | Combinator::SubsequentSibling => {
+                let mut current = anchor.next();
+                while let Some(next) = current {
+                    if matches(&next) || next.find(&matches).next().is_some() {
+                        return true;
+                    }
+                    current = next.next();
+                }
+                false
+            }
+        }
+    }
+
+    // Match right to left, starting from the compound at `index`. The first
+    // compound must be related to `anchor` by its combinator, if given.
+    fn matches_at(&self, index: usize, node: &Node, anchor: Option<(Combinator, &Node)>) -> bool {
+        let name = match *node.data() {
+            Data::Element(ref name, _) => name,
+            _ => return false,
+        };
+
+        if !self.compounds[index]
+            .iter()
+            .all(|simple| simple.matches(node, name))
+        {
+            return false;
+        }
+
+        if index == 0 {
+            return match anchor {
+                Some((combinator, anchor)) => {
+                    combinator.matches(node, |node| node.index() == anchor.index())
+                }
+                None => true,
+            };
+        }
+
+        let index = index - 1;
+        self.combinators[index].matches(node, |node| self.matches_at(index, node, anchor))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+impl Combinator {
+    // Whether `f` holds for a node which `node` is related to by this
+    // combinator, looking left.
+    fn matches<F: Fn(&Node) -> bool>(self, node: &Node, f: F) -> bool {
+        match self {
+            Combinator::Descendant => {
+                let mut current = node.parent();
+                while let Some(parent) = current {
+                    if f(&parent) {
+                        return true;
+                    }
+                    current = parent.parent();
+                }
+                false
+            }
+            Combinator::Child => node.parent().is_some_and(|parent| f(&parent)),
+            Combinator::NextSibling => prev_element(node).is_some_and(|prev| f(&prev)),
+            Combinator::SubsequentSibling => {
+                let mut current = prev_element(node);
+                while let Some(prev) = current {
+                    if f(&prev) {
+                        return true;
+                    }
+                    current = prev_element(&prev);
+                }
+                false
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Simple {
+    Universal,
+    Type(String),
+    Id(String),
+    Class(String),
+    /// An attribute, optionally with an operator, value and whether to
+    /// ignore case.
+    Attr(String, Option<(Operator, String, bool)>),
+    Nth(Nth, i32, i32),
+    OnlyChild,
+    OnlyOfType,
+    Root,
+    Empty,
+    Link,
+    Lang(String),
+    Enabled,
+    Disabled,
+    Checked,
+    /// A user action pseudo-class, which never matches a static document.
+    Never,
+    Not(Box<Simple>),
+    /// Relative selectors, each with its leading combinator.
+    Has(Vec<(Combinator, Complex)>),
+}
+
+impl Simple {
+    fn matches(&self, node: &Node, name: &QualName) -> bool {
+        match *self {
+            Simple::Universal => true,
+            Simple::Type(ref local) => {
+                if name.ns == ns!(html) {
+                    name.local.as_ref().eq_ignore_ascii_case(local)
+                } else {
+                    &*name.local == local
+                }
+            }
+            Simple::Id(ref id) => node.attr("id") == Some(id),
+            Simple::Class(ref class) => node
+                .attr("class")
+                .is_some_and(|classes| classes.split_whitespace().any(|c| c == class)),
+            Simple::Attr(ref attr, ref operator) => match attr_value(node, name, attr) {
+                Some(value) => match *operator {
+                    Some((operator, ref expected, ignore_case)) => {
+                        operator.matches(value, expected, ignore_case)
+                    }
+                    None => true,
+                },
+                None => false,
+            },
+            Simple::Nth(nth, a, b) => {
+                let position = match nth {
+                    Nth::Child => position(node, false, None),
+                    Nth::LastChild => position(node, true, None),
+                    Nth::OfType => position(node, false, Some(name)),
+                    Nth::LastOfType => position(node, true, Some(name)),
+                };
+                nth_matches(a, b, position)
+            }
+            Simple::OnlyChild => {
+                position(node, false, None) == 1 && position(node, true, None) == 1
+            }
+            Simple::OnlyOfType => {
+                position(node, false, Some(name)) == 1 && position(node, true, Some(name)) == 1
+            }
+            Simple::Root => node.parent().is_none(),
+            Simple::Empty => {
+                node.hides_contents()
+                    || node
+                        .children()
+                        .all(|child| !matches!(*child.data(), Data::Element(..) | Data::Text(..)))
+            }
+            Simple::Link => is_html(name, &["a", "area", "link"]) && node.attr("href").is_some(),
+            Simple::Lang(ref lang) => {
+                let mut current = Some(*node);
+                while let Some(node) = current {
+                    if let Some(value) = node.attr("lang") {
+                        return value.eq_ignore_ascii_case(lang)
+                            || value.len() > lang.len()
+                                && value.is_char_boundary(lang.len())
+                                && value[..lang.len()].eq_ignore_ascii_case(lang)
+                                && value.as_bytes()[lang.len()] == b'-';
+                    }
+                    current = node.parent();
+                }
+                false
+            }
+            Simple::Enabled => is_html(name, FORM_ELEMENTS) && node.attr("disabled").is_none(),
+            Simple::Disabled => is_html(name, FORM_ELEMENTS) && node.attr("disabled").is_some(),
+            Simple::Checked => {
+                is_html(name, &["input"]) && node.attr("checked").is_some()
+                    || is_html(name, &["option"]) && node.attr("selected").is_some()
+            }
+            Simple::Never => false,
+            Simple::Not(ref simple) => !simple.matches(node, name),
+            Simple::Has(ref relatives) => relatives
+                .iter()
+                .any(|&(combinator, ref complex)| complex.matches_relative(combinator, node)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Nth {
+    Child,
+    LastChild,
+    OfType,
+    LastOfType,
+}
+
+const FORM_ELEMENTS: &[&str] = &[
+    "button", "fieldset", "input", "optgroup", "option", "select", "textarea",
+];
+
+fn is_html(name: &QualName, locals: &[&str]) -> bool {
+    name.ns == ns!(html) && locals.iter().any(|local| name.local == **local)
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+// Attribute names are matched case-insensitively on HTML elements.
+fn attr_value<'a>(node: &Node<'a>, name: &QualName, attr: &str) -> Option<&'a str> {
+    let html = name.ns == ns!(html);
+    node.attrs()
+        .find(|&(name, _)| {
+            if html {
+                name.eq_ignore_ascii_case(attr)
+            } else {
+                name == attr
+            }
+        })
+        .map(|(_, value)| value)
+}
+
+fn parse_nth(expression: &str) -> Option<(i32, i32)> {
+    let expression = expression
+        .chars()
+        .filter(|&c| !is_whitespace(c))
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    let integer = |s: &str| {
+        if s.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+            s.parse::<i32>().ok()
+        } else {
+            None
+        }
+    };
+
+    match &*expression {
+        "odd" => Some((2, 1)),
+        "even" => Some((2, 0)),
+        _ => match expression.find('n') {
+            Some(n) => {
+                let a = match &expression[..n] {
+                    "" | "+" => 1,
+                    "-" => -1,
+                    a => integer(a)?,
+                };
+                let b = match &expression[n + 1..] {
+                    "" => 0,
+                    b if b.starts_with(['+', '-']) => integer(b)?,
+                    _ => return None,
+                };
+                Some((a, b))
+            }
+            None => Some((0, integer(&expression)?)),
+        },
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic() || !c.is_ascii()
+}
+
+fn is_name(c: char) -> bool {
+    is_name_start(c) || c == '-' || c.is_ascii_digit()
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    // Whether the arguments of a `:has()` are being parsed.
+    in_has: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.position..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek().is_some_and(is_whitespace) {
+            self.bump();
+        }
+        self.position != start
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> ParseError {
+        self.error_at(self.position, message)
+    }
+
+    fn error_at<M: Into<String>>(&self, position: usize, message: M) -> ParseError {
+        ParseError {
+            column: self.input[..position].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<Complex>, ParseError> {
+        let mut complexes = vec![];
+        loop {
+            self.skip_whitespace();
+            complexes.push(self.parse_complex()?);
+            if !self.eat(',') {
+                return Ok(complexes);
+            }
+        }
+    }
+
+    fn parse_complex(&mut self) -> Result<Complex, ParseError> {
+        let mut compounds = vec![self.parse_compound()?];
+        let mut combinators = vec![];
+
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                None | Some(',') | Some(')') => break,
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                _ if whitespace => Combinator::Descendant,
+                Some(c) => return Err(self.error(format!("unexpected character {:?}", c))),
+            };
+            if combinator != Combinator::Descendant {
+                self.bump();
+                self.skip_whitespace();
+            }
+            combinators.push(combinator);
+            compounds.push(self.parse_compound()?);
+        }
+
+        Ok(Complex {
+            compounds,
+            combinators,
+        })
+    }
+
+    fn parse_relative_list(&mut self) -> Result<Vec<(Combinator, Complex)>, ParseError> {
+        let mut relatives = vec![];
+        loop {
+            self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                _ => Combinator::Descendant,
+            };
+            if combinator != Combinator::Descendant {
+                self.bump();
+                self.skip_whitespace();
+            }
+            relatives.push((combinator, self.parse_complex()?));
+            if !self.eat(',') {
+                return Ok(relatives);
+            }
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<Vec<Simple>, ParseError> {
+        let mut compound = vec![];
+        if let Some(simple) = self.parse_type()? {
+            compound.push(simple);
+        }
+        while let Some(simple) = self.parse_simple(false)? {
+            compound.push(simple);
+        }
+        if compound.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => self.error(format!("expected selector, found {:?}", c)),
+                None => self.error("expected selector"),
+            });
+        }
+        Ok(compound)
+    }
+
+    fn parse_type(&mut self) -> Result<Option<Simple>, ParseError> {
+        let start = self.position;
+        let name = if self.eat('*') {
+            None
+        } else if self.starts_ident() {
+            Some(self.parse_ident()?)
+        } else {
+            return Ok(None);
+        };
+
+        // Only the "any namespace" prefix can be used without declarations.
+        let name = if self.peek() == Some('|') && self.peek_nth(1) != Some('=') {
+            if name.is_some() {
+                return Err(self.error_at(start, "namespace prefixes are not supported"));
+            }
+            self.bump();
+            if self.eat('*') {
+                None
+            } else {
+                Some(self.parse_ident()?)
+            }
+        } else {
+            name
+        };
+
+        Ok(Some(name.map_or(Simple::Universal, Simple::Type)))
+    }
+
+    fn parse_simple(&mut self, negated: bool) -> Result<Option<Simple>, ParseError> {
+        match self.peek() {
+            Some('#') => {
+                self.bump();
+                Ok(Some(Simple::Id(self.parse_name()?)))
+            }
+            Some('.') => {
+                self.bump();
+                Ok(Some(Simple::Class(self.parse_ident()?)))
+            }
+            Some('[') => self.parse_attr().map(Some),
+            Some(':') => self.parse_pseudo(negated).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_attr(&mut self) -> Result<Simple, ParseError> {
+        self.bump();
+        self.skip_whitespace();
+
+        let start = self.position;
+        if self.peek() == Some('*') || self.peek() == Some('|') {
+            return Err(self.error("namespace prefixes are not supported"));
+        }
+        let name = self.parse_ident()?;
+        if self.peek() == Some('|') && self.peek_nth(1) != Some('=') {
+            return Err(self.error_at(start, "namespace prefixes are not supported"));
+        }
+        self.skip_whitespace();
+
+        let operator = match self.peek() {
+            Some(']') => {
+                self.bump();
+                return Ok(Simple::Attr(name, None));
+            }
+            Some('=') => Operator::Equals,
+            Some(c) if self.peek_nth(1) == Some('=') => match c {
+                '~' => Operator::Includes,
+                '|' => Operator::DashMatch,
+                '^' => Operator::Prefix,
+                '$' => Operator::Suffix,
+                '*' => Operator::Substring,
+                _ => return Err(self.error("expected attribute operator or ']'")),
+            },
+            _ => return Err(self.error("expected attribute operator or ']'")),
+        };
+        if operator != Operator::Equals {
+            self.bump();
+        }
+        self.bump();
+        self.skip_whitespace();
+
+        let value = match self.peek() {
+            Some('"') | Some('\'') => self.parse_string()?,
+            _ if self.starts_ident() => self.parse_ident()?,
+            _ => return Err(self.error("expected attribute value")),
+        };
+        self.skip_whitespace();
+
+        let mut ignore_case = false;
+        if self.starts_ident() {
+            let start = self.position;
+            match &*self.parse_ident()?.to_ascii_lowercase() {
+                "i" => ignore_case = true,
+                "s" => {}
+                flag => {
+                    return Err(self.error_at(start, format!("unknown attribute flag {}", flag)))
+                }
+            }
+            self.skip_whitespace();
+        }
+
+        if !self.eat(']') {
+            return Err(self.error("expected ']'"));
+        }
+
+        Ok(Simple::Attr(name, Some((operator, value, ignore_case))))
+    }
+
+    fn parse_pseudo(&mut self, negated: bool) -> Result<Simple, ParseError> {
+        let start = self.position;
+        self.bump();
+        if self.peek() == Some(':') {
+            return Err(self.error_at(start, "pseudo-elements are not supported"));
+        }
+        let name = self.parse_ident()?.to_ascii_lowercase();
+
+        if self.eat('(') {
+            self.skip_whitespace();
+            let simple = match &*name {
+                "nth-child" => self.parse_nth(Nth::Child)?,
+                "nth-last-child" => self.parse_nth(Nth::LastChild)?,
+                "nth-of-type" => self.parse_nth(Nth::OfType)?,
+                "nth-last-of-type" => self.parse_nth(Nth::LastOfType)?,
+                "lang" => Simple::Lang(self.parse_ident()?),
+                "not" if !negated => {
+                    let simple = match self.parse_type()? {
+                        Some(simple) => simple,
+                        None => match self.parse_simple(true)? {
+                            Some(simple) => simple,
+                            None => return Err(self.error("expected selector")),
+                        },
+                    };
+                    Simple::Not(Box::new(simple))
+                }
+                "not" => return Err(self.error_at(start, ":not() cannot be nested")),
+                "has" if !self.in_has => {
+                    self.in_has = true;
+                    let relatives = self.parse_relative_list()?;
+                    self.in_has = false;
+                    Simple::Has(relatives)
+                }
+                "has" => return Err(self.error_at(start, ":has() cannot be nested")),
+                _ => {
+                    return Err(
+                        self.error_at(start, format!("unsupported pseudo-class :{}()", name))
+                    )
+                }
+            };
+            self.skip_whitespace();
+            if !self.eat(')') {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(simple);
+        }
+
+        Ok(match &*name {
+            "root" => Simple::Root,
+            "first-child" => Simple::Nth(Nth::Child, 0, 1),
+            "last-child" => Simple::Nth(Nth::LastChild, 0, 1),
+            "first-of-type" => Simple::Nth(Nth::OfType, 0, 1),
+            "last-of-type" => Simple::Nth(Nth::LastOfType, 0, 1),
+            "only-child" => Simple::OnlyChild,
+            "only-of-type" => Simple::OnlyOfType,
+            "empty" => Simple::Empty,
+            "link" => Simple::Link,
+            "enabled" => Simple::Enabled,
+            "disabled" => Simple::Disabled,
+            "checked" => Simple::Checked,
+            "visited" | "hover" | "active" | "focus" | "target" => Simple::Never,
+            "first-line" | "first-letter" | "before" | "after" => {
+                return Err(self.error_at(start, "pseudo-elements are not supported"))
+            }
+            _ => return Err(self.error_at(start, format!("unsupported pseudo-class :{}", name))),
+        })
+    }
+
+    fn parse_nth(&mut self, nth: Nth) -> Result<Simple, ParseError> {
+        let start = self.position;
+        let end = self.input[start..]
+            .find(')')
+            .map_or(self.input.len(), |end| start + end);
+        let (a, b) = parse_nth(&self.input[start..end])
+            .ok_or_else(|| self.error_at(start, "invalid nth expression"))?;
+        self.position = end;
+        Ok(Simple::Nth(nth, a, b))
+    }
+
+    fn starts_ident(&self) -> bool {
+        let mut chars = self.input[self.position..].chars();
+        let valid_escape = |c: Option<char>| c.is_some_and(|c| !matches!(c, '\n' | '\r' | '\x0C'));
+        match chars.next() {
+            Some('-') => match chars.next() {
+                Some('\\') => valid_escape(chars.next()),
+                Some(c) => is_name_start(c) || c == '-',
+                None => false,
+            },
+            Some('\\') => valid_escape(chars.next()),
+            Some(c) => is_name_start(c),
+            None => false,
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, ParseError> {
+        if !self.starts_ident() {
+            return Err(match self.peek() {
+                Some(c) => self.error(format!("expected identifier, found {:?}", c)),
+                None => self.error("expected identifier"),
+            });
+        }
+        self.parse_name()
+    }
+
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') => name.push(self.parse_escape()?),
+                Some(c) if is_name(c) => {
+                    self.bump();
+                    name.push(c);
+                }
+                _ => break,
+            }
+        }
+        if name.is_empty() {
+            return Err(self.error("expected name"));
+        }
+        Ok(name)
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let start = self.position;
+        self.bump();
+        match self.peek() {
+            None | Some('\n') | Some('\r') | Some('\x0C') => {
+                Err(self.error_at(start, "invalid escape"))
+            }
+            Some(c) if c.is_ascii_hexdigit() => {
+                let mut value = 0;
+                for _ in 0..6 {
+                    match self.peek().and_then(|c| c.to_digit(16)) {
+                        Some(digit) => {
+                            self.bump();
+                            value = value * 16 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                if self.eat('\r') {
+                    self.eat('\n');
+                } else if self.peek().is_some_and(is_whitespace) {
+                    self.bump();
+                }
+                Ok(match char::from_u32(value) {
+                    Some(c) if value != 0 => c,
+                    _ => '\u{FFFD}',
+                })
+            }
+            Some(c) => {
+                self.bump();
+                Ok(c)
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        let quote = self.bump();
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(start, "unterminated string")),
+                Some('\n') | Some('\r') | Some('\x0C') => {
+                    return Err(self.error("unexpected newline in string"))
+                }
+                Some('\\') => match self.peek_nth(1) {
+                    // An escaped newline continues the string on the next line.
+                    Some('\r') => {
+                        self.bump();
+                        self.bump();
+                        self.eat('\n');
+                    }
+                    Some('\n') | Some('\x0C') => {
+                        self.bump();
+                        self.bump();
+                    }
+                    None => return Err(self.error_at(start, "unterminated string")),
+                    Some(_) => string.push(self.parse_escape()?),
+                },
+                c if c == quote => {
+                    self.bump();
+                    return Ok(string);
+                }
+                Some(c) => {
+                    self.bump();
+                    string.push(c);
+                }
+            }
+        }
+    }
+}
@@ -59,6 +59,214 @@ speculate! {
             assert_eq!(k.parent(), Some(j));
         }
 
+        test "Document::from(&str) matches markup5ever_rcdom" {
+            use html5ever::serialize::{serialize, SerializeOpts};
+            use html5ever::tendril::TendrilSink;
+            use markup5ever_rcdom::{RcDom, SerializableHandle};
+
+            let htmls = [
+                include_str!("fixtures/struct.Vec.html"),
+                // Adoption agency algorithm.
+                "<p>1<b>2<i>3</b>4</i>5</p><a>x<div>y</a>z</div>",
+                // Foster parenting and text merging.
+                "<table>a<tr>b<td>c</td>d</tr>e</table><table><tr><td>x</table>y",
+                // Attributes added to existing elements.
+                "<html a=b><body c=d><html e=f a=x><body g=h>",
+                "<math><annotation-xml encoding='text/html'><div>x</div></annotation-xml></math>",
+                "<svg><foreignObject><p>x</svg><select><option>a<option>b</select><!--c-->",
+            ];
+
+            for html in htmls.iter() {
+                let document = Document::from(*html);
+                let rc_dom = html5ever::parse_document(RcDom::default(), Default::default()).one(*html);
+
+                let mut expected = vec![];
+                let opts = SerializeOpts::default();
+                serialize(&mut expected, &SerializableHandle::from(rc_dom.document), opts).unwrap();
+
+                let actual = document
+                    .nodes
+                    .iter()
+                    .filter(|raw| raw.parent.is_none())
+                    .map(|raw| document.nth(raw.index).unwrap().html())
+                    .collect::<String>();
+                assert_eq!(actual, String::from_utf8(expected).unwrap());
+            }
+        }
+
+        test "Document::from(&str) with a doctype" {
+            let document = Document::from("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \
+                                           \"http://www.w3.org/TR/html4/strict.dtd\"><p>");
+            let doctype = document.nth(0).unwrap();
+            assert_eq!(doctype.data(), &node::Data::Doctype {
+                name: "html".into(),
+                public_id: "-//W3C//DTD HTML 4.01//EN".into(),
+                system_id: "http://www.w3.org/TR/html4/strict.dtd".into(),
+            });
+            assert_eq!(doctype.parent(), None);
+            assert_eq!(doctype.next().unwrap().name(), Some("html"));
+            assert_eq!(doctype.html(), "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \
+                                        \"http://www.w3.org/TR/html4/strict.dtd\">");
+
+            for html in ["<!DOCTYPE html>",
+                         "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\">",
+                         "<!DOCTYPE html SYSTEM \"about:legacy-compat\">",
+                         "<!DOCTYPE html SYSTEM 'a\"b'>"] {
+                let document = Document::from(html);
+                let doctype = document.nth(0).unwrap();
+                assert_eq!(doctype.html(), html);
+                assert_eq!(Document::from(doctype.html().as_str()).nth(0).unwrap().data(), doctype.data());
+            }
+        }
+
+        test "Document::from(&str) with deeply nested elements" {
+            let document = Document::from("<div>".repeat(10_000).as_str());
+            assert_eq!(document.nodes.len(), 10_003);
+            assert_eq!(document.nodes[10_002].parent, Some(10_001));
+        }
+
+        test "Document::from_fragment()" {
+            use select::predicate::*;
+
+            let document = Document::from_fragment("<td>a</td><td>b</td>", "tr");
+            assert_eq!(document.nodes.len(), 4);
+
+            let a = document.nth(0).unwrap();
+            let b = document.nth(2).unwrap();
+            assert_eq!(a.name(), Some("td"));
+            assert_eq!(a.parent(), None);
+            assert_eq!(a.next(), Some(b));
+            assert_eq!(b.text(), "b");
+            assert_eq!(document.find(Name("td")).count(), 2);
+
+            // Without the context, the cells are dropped by the parser.
+            assert_eq!(Document::from("<td>a</td><td>b</td>").find(Name("td")).count(), 0);
+
+            let document = Document::from_fragment("<li>x</li><!--y-->z", "ul");
+            assert_eq!(document.nodes.len(), 4);
+            assert_eq!(document.nth(0).unwrap().html(), "<li>x</li>");
+            assert_eq!(document.nth(2).unwrap().as_comment(), Some("y"));
+            assert_eq!(document.nth(3).unwrap().as_text(), Some("z"));
+            assert_eq!(document.find(Name("html")).count(), 0);
+            assert_eq!(document.find(Name("body")).count(), 0);
+        }
+
+        test "Document::from_xml()" {
+            use select::predicate::*;
+
+            let document = Document::from_xml(r#"<?xml version="1.0"?>
+<rss xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+<atom:link href="https://example.com/feed"/>
+<Item><title><![CDATA[<b>Bold</b> & more]]></title></Item>
+<item><title>Plain</title></item>
+</channel></rss>"#);
+
+            assert!(document.nth(0).unwrap().is(ProcessingInstruction));
+            assert_eq!(document.find(Name("html")).count(), 0);
+            assert_eq!(document.find(Name("item")).count(), 1);
+            assert_eq!(document.find(Name("Item")).count(), 1);
+
+            let titles = document.find(Name("title")).map(|title| title.text()).collect::<Vec<_>>();
+            assert_eq!(titles, vec!["<b>Bold</b> & more", "Plain"]);
+
+            let link = document.find(Name("link")).next().unwrap();
+            assert_eq!(link.attr("href"), Some("https://example.com/feed"));
+            match *link.data() {
+                node::Data::Element(ref name, _) => {
+                    assert_eq!(name.prefix.as_deref(), Some("atom"));
+                    assert_eq!(&*name.ns, "http://www.w3.org/2005/Atom");
+                    assert_eq!(&*name.local, "link");
+                }
+                _ => unreachable!(),
+            }
+
+            let titles = document.select("Item > title").unwrap().count();
+            assert_eq!(titles, 1);
+
+            let document = Document::from_xml("<a xmlns:x='u'><x:b x:c='1'/><?pi data?></a>");
+            let a = document.nth(0).unwrap();
+            assert_eq!(a.xml(), r#"<a><x:b xmlns:x="u" x:c="1"></x:b><?pi data?></a>"#);
+            assert_eq!(a.html(), r#"<a><b unknown_namespace:c="1"></b><?pi data></a>"#);
+        }
+
+        test "Document::parse_with()" {
+            use select::document::ParseOpts;
+            use select::predicate::*;
+
+            let html = "<!DOCTYPE html><noscript><p>x</p></noscript>";
+            let document = Document::parse_with(ParseOpts::default(), html);
+            assert_eq!(document.find(Name("p")).count(), 0);
+            let opts = ParseOpts { scripting_enabled: false, ..ParseOpts::default() };
+            let document = Document::parse_with(opts, html);
+            assert_eq!(document.find(Name("p")).count(), 1);
+
+            let html = "<p>x</p>";
+            assert_eq!(Document::parse_with(ParseOpts::default(), html).errors().len(), 1);
+            let opts = ParseOpts { iframe_srcdoc: true, ..ParseOpts::default() };
+            assert_eq!(Document::parse_with(opts, html).errors().len(), 0);
+
+            let html = "<!DOCTYPE html><div>\n  <p>a</p>\n  b\n</div>";
+            assert_eq!(Document::parse_with(ParseOpts::default(), html).find(Text).count(), 3);
+            let opts = ParseOpts { drop_whitespace: true, ..ParseOpts::default() };
+            let document = Document::parse_with(opts, html);
+            assert_eq!(document.find(Text).count(), 2);
+            let div = document.find(Name("div")).next().unwrap();
+            assert_eq!(div.children().count(), 2);
+            assert_eq!(div.first_child().unwrap().next().unwrap().as_text(), Some("\n  b\n"));
+        }
+
+        test "Document::find() with templates" {
+            use select::predicate::*;
+
+            let html = "<template><p>a</p><template><p>b</p></template></template><p>c</p>";
+            let document = Document::from(html);
+            assert_eq!(document.find(Name("p")).count(), 1);
+            assert_eq!(document.find(Name("template")).count(), 1);
+            assert_eq!(document.select("p").unwrap().count(), 1);
+            let body = document.find(Name("body")).into_selection();
+            assert_eq!(body.find(Name("p")).len(), 1);
+
+            let template = document.find(Name("template")).next().unwrap();
+            assert_eq!(template.html(),
+                       "<template><p>a</p><template><p>b</p></template></template>");
+            assert_eq!(template.find(Name("p")).count(), 1);
+            assert_eq!(template.find(Name("template")).count(), 1);
+
+            assert_eq!(document.find_in_templates(Name("p")).count(), 3);
+            assert_eq!(document.find_in_templates(Name("template")).count(), 2);
+            let head = document.find(Name("head")).next().unwrap();
+            assert_eq!(head.find(Name("p")).count(), 0);
+            assert_eq!(head.find_in_templates(Name("p")).count(), 2);
+
+            let document = Document::from("<table><template><td>x</template></table>");
+            assert_eq!(document.find(Name("table")).next().unwrap().html(),
+                       "<table><template><td>x</td></template></table>");
+        }
+
+        test "Document::errors()" {
+            use select::document::ParseOpts;
+
+            let html = "<!DOCTYPE html>\n<p>a</p>\n\n</div><b a=1 a=2></b>";
+            let document = Document::from(html);
+            let errors = document
+                .errors()
+                .iter()
+                .map(|error| (error.line(), error.message()))
+                .collect::<Vec<_>>();
+            assert_eq!(errors, vec![(4, "Unexpected token"), (4, "Duplicate attribute")]);
+            assert_eq!(document.errors()[0].to_string(), "Unexpected token at line 4");
+            let nodes = Document::from(document.nodes.clone());
+            assert!(nodes.errors().is_empty());
+            assert_eq!(nodes, document);
+
+            let opts = ParseOpts { exact_errors: true, ..ParseOpts::default() };
+            let document = Document::parse_with(opts, html);
+            assert_eq!(document.errors().len(), 2);
+            assert!(document.errors()[0].message().contains("div"));
+
+            assert!(Document::from("<!DOCTYPE html><p>").errors().is_empty());
+        }
+
         test "Docucment::from_read()" {
             use select::predicate::*;
             use std::io::Cursor;
@@ -71,11 +279,81 @@ speculate! {
             assert_eq!(document.unwrap().find(Name("p")).count(), 1);
         }
 
+        test "Document::from_read_with_charset()" {
+            use select::predicate::*;
+            use std::io::Cursor;
+
+            let html = "<html><head><meta charset=shift_jis></head><body><p>日本語</p></body></html>";
+            let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(html);
+            let (document, encoding) = Document::from_read_with_charset(Cursor::new(bytes), None).unwrap();
+            assert_eq!(encoding, encoding_rs::SHIFT_JIS);
+            assert_eq!(document.find(Name("p")).next().unwrap().text(), "日本語");
+
+            let (bytes, _, _) = encoding_rs::GBK.encode("<p>中文</p>");
+            let (document, encoding) = Document::from_read_with_charset(Cursor::new(bytes), Some("gbk")).unwrap();
+            assert_eq!(encoding, encoding_rs::GBK);
+            assert_eq!(document.find(Name("p")).next().unwrap().text(), "中文");
+
+            let bytes = b"<p>caf\xE9</p>";
+            let document = Document::from_read(Cursor::new(bytes)).unwrap();
+            assert_eq!(document.find(Name("p")).next().unwrap().text(), "café");
+
+            let bytes = b"\xEF\xBB\xBF<p>x</p>";
+            let (document, encoding) = Document::from_read_with_charset(Cursor::new(bytes), None).unwrap();
+            assert_eq!(encoding, encoding_rs::UTF_8);
+            assert_eq!(document.find(Name("body")).next().unwrap().html(), "<body><p>x</p></body>");
+        }
+
+        test "DocumentBuilder" {
+            use html5ever::tendril::{ByteTendril, TendrilSink};
+            use select::document::DocumentBuilder;
+            use select::predicate::*;
+
+            let html = include_str!("fixtures/struct.Vec.html");
+            let expected = Document::from(html);
+            for &size in &[1, 7, 1000, 4096, 1 << 20] {
+                let mut builder = DocumentBuilder::new();
+                for chunk in html.as_bytes().chunks(size) {
+                    builder.process(ByteTendril::from_slice(chunk));
+                }
+                assert_eq!(builder.encoding(), Some(encoding_rs::UTF_8));
+                assert_eq!(builder.finish(), expected);
+            }
+
+            let html = "<meta charset=shift_jis><p>日本語</p>";
+            let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(html);
+            let mut builder = DocumentBuilder::new();
+            for byte in bytes.iter() {
+                builder.process(ByteTendril::from_slice(&[*byte]));
+            }
+            assert_eq!(builder.encoding(), None);
+            let document = builder.finish();
+            assert_eq!(document.find(Name("p")).next().unwrap().text(), "日本語");
+
+            let (bytes, _, _) = encoding_rs::GBK.encode("<p>中文</p>");
+            let document = DocumentBuilder::with_charset("gbk").read_from(&mut &bytes[..]).unwrap();
+            assert_eq!(document.find(Name("p")).next().unwrap().text(), "中文");
+
+            let document = DocumentBuilder::new().one(&b"\xEF\xBB\xBF<p>x</p>"[..]);
+            assert_eq!(document.find(Name("body")).next().unwrap().html(), "<body><p>x</p></body>");
+
+            // Invalid UTF-8 after the first 1024 bytes decodes the same way as
+            // with Document::from_read.
+            let mut bytes = format!("<p>{}</p><p>", "é".repeat(600)).into_bytes();
+            bytes.extend_from_slice(b"caf\xE9</p>");
+            let document = DocumentBuilder::new().one(&bytes[..]);
+            let (expected, encoding) =
+                Document::from_read_with_charset(std::io::Cursor::new(&bytes), None).unwrap();
+            assert_eq!(encoding, encoding_rs::UTF_8);
+            assert_eq!(document, expected);
+            assert_eq!(document.find(Name("p")).nth(1).unwrap().text(), "caf\u{FFFD}");
+        }
+
         test "Document::find()" {
             use select::predicate::*;
 
             let document = Document::from(include_str!("fixtures/struct.Vec.html"));
-            assert_eq!(document.find(Any).count(), 11446);
+            assert_eq!(document.find(Any).count(), 11447);
             assert_eq!(document.find(Name("div")).count(), 208);
             assert_eq!(document.find(Attr("id", "main")).count(), 1);
             assert_eq!(document.find(Class("struct")).count(), 168);
@@ -0,0 +1,68 @@
+pub use encoding_rs::*;
+pub use select::encoding::sniff;
+
+use speculate::speculate;
+
+speculate! {
+    describe "encoding" {
+        test "sniff() with a BOM" {
+            assert_eq!(sniff(b"\xEF\xBB\xBF<p>", Some("windows-1252")), UTF_8);
+            assert_eq!(sniff(b"\xFF\xFE<\x00", None), UTF_16LE);
+            assert_eq!(sniff(b"\xFE\xFF\x00<", None), UTF_16BE);
+        }
+
+        test "sniff() with a transport charset" {
+            assert_eq!(sniff(b"<meta charset=utf-8>", Some("Shift_JIS")), SHIFT_JIS);
+            assert_eq!(sniff(b"<meta charset=utf-8>", Some("bogus")), UTF_8);
+        }
+
+        test "sniff() with <meta charset>" {
+            assert_eq!(sniff(b"<meta charset=gbk>", None), GBK);
+            assert_eq!(sniff(b"<META CHARSET='Shift_JIS'>", None), SHIFT_JIS);
+            assert_eq!(sniff(b"<head><meta name=x charset=\"euc-jp\"/>", None), EUC_JP);
+            assert_eq!(sniff(b"<meta charset=utf-16>", None), UTF_8);
+            assert_eq!(sniff(b"<meta charset=x-user-defined>", None), WINDOWS_1252);
+        }
+
+        test "sniff() with <meta http-equiv>" {
+            let html = b"<meta http-equiv=Content-Type content='text/html; charset=koi8-r'>";
+            assert_eq!(sniff(html, None), KOI8_R);
+            let html = b"<meta content=\"text/html;charset = 'iso-8859-2'\" http-equiv=\"content-type\">";
+            assert_eq!(sniff(html, None), ISO_8859_2);
+            // The content attribute is ignored without the pragma.
+            assert_eq!(sniff(b"<meta content='text/html; charset=koi8-r'>", None), UTF_8);
+            // A charset attribute doesn't replace a charset found in content.
+            let html = b"<meta http-equiv=content-type content='charset=koi8-r' charset=gbk>";
+            assert_eq!(sniff(html, None), KOI8_R);
+            let html = b"<meta charset=gbk http-equiv=content-type content='charset=koi8-r'>";
+            assert_eq!(sniff(html, None), GBK);
+            let html = b"<meta charset=bogus http-equiv=content-type content='charset=koi8-r'>";
+            assert_eq!(sniff(html, None), KOI8_R);
+        }
+
+        test "sniff() skips comments and other tags" {
+            assert_eq!(sniff(b"<!-- <meta charset=gbk> --><meta charset=big5>", None), BIG5);
+            assert_eq!(sniff(b"<p title='<meta charset=gbk>'><meta charset=big5>", None), BIG5);
+            assert_eq!(sniff(b"<!doctype html><?xml?></x><meta charset=big5>", None), BIG5);
+        }
+
+        test "sniff() only prescans 1024 bytes" {
+            let mut html = vec![b' '; 1024];
+            html.extend_from_slice(b"<meta charset=gbk>");
+            assert_eq!(sniff(&html, None), UTF_8);
+        }
+
+        test "sniff() fallback" {
+            assert_eq!(sniff("<p>é</p>".as_bytes(), None), UTF_8);
+            assert_eq!(sniff(b"<p>\xE9</p>", None), WINDOWS_1252);
+
+            // Only the first 1024 bytes are checked.
+            let mut html = "é".repeat(512).into_bytes();
+            html.extend_from_slice(b"\xE9");
+            assert_eq!(sniff(&html, None), UTF_8);
+            let mut html = vec![b' '; 1023];
+            html.extend_from_slice("é".as_bytes());
+            assert_eq!(sniff(&html, None), UTF_8);
+        }
+    }
+}
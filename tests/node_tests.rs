@@ -196,6 +196,124 @@ speculate! {
             assert_eq!(comment.inner_html(), "");
         }
 
+        test "Node::source_span()" {
+            use select::document::ParseOpts;
+
+            let html = "<!DOCTYPE html>\n<p class=a>x &amp; y<p>z\n<ul>\n<li>é<li><b>w</B ></ul>";
+            let opts = ParseOpts { source_spans: true, ..ParseOpts::default() };
+            let document = Document::parse_with(opts, html);
+            let span = |index: usize| {
+                let span = document.nth(index).unwrap().source_span().unwrap();
+                (span.line, span.column, span.start, span.end)
+            };
+            assert_eq!(span(0), (1, 1, 0, 15));
+            assert_eq!(span(1), (2, 1, 16, 70));
+            assert_eq!(span(2), (2, 1, 16, 16));
+            assert_eq!(span(4), (2, 1, 16, 36));
+            assert_eq!(span(5), (2, 12, 27, 36));
+            assert_eq!(span(10), (4, 1, 46, 52));
+            assert_eq!(span(11), (4, 5, 50, 52));
+            assert_eq!(span(12), (4, 6, 52, 65));
+
+            assert_eq!(Document::from(html).nth(0).unwrap().source_span(), None);
+            assert_eq!(Document::from_xml("<a/>").nth(0).unwrap().source_span(), None);
+        }
+
+        test "Node::source_html()" {
+            use select::document::ParseOpts;
+            use select::predicate::*;
+
+            let html = "<!DOCTYPE html>\n<p class=a>x &amp; y<p>z\n<ul>\n<li>é<li><b>w</B ></ul>";
+            let opts = ParseOpts { source_spans: true, ..ParseOpts::default() };
+            let document = Document::parse_with(opts.clone(), html);
+            let source_html = |index: usize| document.nth(index).unwrap().source_html().unwrap();
+            assert_eq!(source_html(0), "<!DOCTYPE html>");
+            assert_eq!(source_html(2), "");
+            assert_eq!(source_html(4), "<p class=a>x &amp; y");
+            assert_eq!(source_html(5), "x &amp; y");
+            assert_eq!(source_html(8), "<ul>\n<li>é<li><b>w</B ></ul>");
+            assert_eq!(source_html(12), "<li><b>w</B >");
+            assert_eq!(source_html(13), "<b>w</B >");
+
+            let document = Document::from_fragment_with(opts.clone(), "<td>a</td><td>b", "tr");
+            assert_eq!(document.nth(0).unwrap().source_html(), Some("<td>a</td>"));
+            assert_eq!(document.nth(2).unwrap().source_html(), Some("<td>b"));
+            assert_eq!(Document::from_fragment("<td>a</td>", "tr").nth(0).unwrap().source_html(), None);
+            assert_eq!(Document::from_xml("<a/>").nth(0).unwrap().source_html(), None);
+
+            // Text in tables is only inserted once the next tag is seen.
+            let document = Document::parse_with(opts.clone(), "<table>\n  <tr><td>a</td></tr>\n</table>");
+            let texts = document.find(Text).map(|text| text.source_html().unwrap()).collect::<Vec<_>>();
+            assert_eq!(texts, vec!["\n  ", "a", "\n"]);
+            let source_html = |name: &str| document.find(Name(name)).next().unwrap().source_html().unwrap();
+            assert_eq!(source_html("table"), "<table>\n  <tr><td>a</td></tr>\n</table>");
+            assert_eq!(source_html("tbody"), "<tr><td>a</td></tr>\n");
+            assert_eq!(source_html("tr"), "<tr><td>a</td></tr>");
+
+            // Dropped whitespace still belongs to its parent's source.
+            let dropping = ParseOpts { drop_whitespace: true, ..opts.clone() };
+            let document = Document::parse_with(dropping, "<ul>\n  <li>a</li>\n  <li>b</li>\n</ul>");
+            let ul = document.find(Name("ul")).next().unwrap();
+            assert_eq!(ul.source_html(), Some("<ul>\n  <li>a</li>\n  <li>b</li>\n</ul>"));
+            assert_eq!(ul.children().count(), 2);
+
+            // Foster-parented text is moved before the table.
+            let document = Document::parse_with(opts, "<table><tr><td>a</td></tr>x</table>");
+            let texts = document.find(Text).map(|text| text.source_html().unwrap()).collect::<Vec<_>>();
+            assert_eq!(texts, vec!["x", "a"]);
+            let source_html = |name: &str| document.find(Name(name)).next().unwrap().source_html().unwrap();
+            assert_eq!(source_html("table"), "<table><tr><td>a</td></tr>x</table>");
+            assert_eq!(source_html("body"), "<table><tr><td>a</td></tr>x</table>");
+        }
+
+        test "Node::template_contents()" {
+            let document = Document::from("<template><p>a</p>b</template><p>c</p>");
+            let template = document.nth(2).unwrap();
+            let contents = template.template_contents().unwrap();
+            assert_eq!(contents.map(|node| node.html()).collect::<Vec<_>>(), vec!["<p>a</p>", "b"]);
+            assert_eq!(template.html(), "<template><p>a</p>b</template>");
+            assert!(document.nth(0).unwrap().template_contents().is_none());
+            assert!(foo.template_contents().is_none());
+
+            // Walking the tree does not skip template contents.
+            use select::predicate::{Descendant, Name, Predicate, Text};
+            let html = document.nth(0).unwrap();
+            assert_eq!(html.descendants().filter(|node| node.is(Name("p"))).count(), 2);
+            let p = template.first_child().unwrap();
+            assert!(Descendant(Name("template"), Name("p")).matches(&p));
+            assert_eq!(html.find(Name("p")).count(), 1);
+            assert_eq!(html.find(Text).map(|text| text.text()).collect::<Vec<_>>(), vec!["c"]);
+            assert_eq!(html.find_in_templates(Name("p")).count(), 2);
+            assert_eq!(template.find_in_templates(Text).count(), 2);
+        }
+
+        test "template contents are not text" {
+            use select::predicate::*;
+
+            let document = Document::from("<body><template><p>hidden</p></template><p>shown</p>");
+            let body = document.find(Name("body")).next().unwrap();
+            let template = document.find(Name("template")).next().unwrap();
+            assert_eq!(body.text(), "shown");
+            assert_eq!(template.text(), "");
+            assert_eq!(document.find(Name("body")).into_selection().texts(), vec!["shown"]);
+            assert_eq!(document.find(Name("template")).into_selection().text(","), "");
+
+            assert!(ContainsText("shown").matches(&body));
+            assert!(!ContainsText("hidden").matches(&body));
+            assert!(!ContainsText("hidden").matches(&template));
+            assert!(TextEquals("shown").matches(&body));
+            assert!(TextEquals("").matches(&template));
+            assert!(!OwnTextContains("hidden").matches(&template));
+            assert!(Empty.matches(&template));
+            assert!(!HasChild(Name("p")).matches(&template));
+            assert_eq!(document.select("template:empty").unwrap().count(), 1);
+            assert_eq!(document.select("p:empty").unwrap().count(), 0);
+
+            let p = template.first_child().unwrap();
+            assert_eq!(p.text(), "hidden");
+            assert!(ContainsText("hidden").matches(&p));
+        }
+
         test "Node::children()" {
             let mut children = html.children();
             assert_eq!(children.next().unwrap().name(), Some("head"));
@@ -226,6 +344,25 @@ speculate! {
             assert_eq!(attrs.next(), None);
         }
 
+        test "Node::namespace() / Node::attr_ns()" {
+            assert_eq!(html.namespace(), Some("http://www.w3.org/1999/xhtml"));
+            assert_eq!(foo.namespace(), None);
+            assert_eq!(body.attr_ns("", "id"), Some("something"));
+            assert_eq!(body.attr_ns("http://www.w3.org/1999/xhtml", "id"), None);
+            assert_eq!(foo.attr_ns("", "id"), None);
+
+            let document = Document::from("<title>a</title>\
+<svg><title>b</title><use xlink:href='#c' href='#d'/></svg>");
+            let svg = document.find(select::predicate::Name("svg")).next().unwrap();
+            let title = svg.first_child().unwrap();
+            let use_ = svg.last_child().unwrap();
+            assert_eq!(svg.namespace(), Some("http://www.w3.org/2000/svg"));
+            assert_eq!(title.namespace(), Some("http://www.w3.org/2000/svg"));
+            assert_eq!(use_.attr_ns("http://www.w3.org/1999/xlink", "href"), Some("#c"));
+            assert_eq!(use_.attr_ns("", "href"), Some("#d"));
+            assert_eq!(use_.attr("href"), Some("#c"));
+        }
+
         test "std::fmt::Debug for Node" {
             assert_eq!(format!("{:?}", bar).replace(' ', ""), r#"Element {
                 name: "bar",
@@ -248,6 +385,10 @@ speculate! {
             }"#.replace(['\n', ' '], ""));
 
             assert_eq!(format!("{:?}", comment), "Comment(\"comment\")");
+
+            let document = Document::from("<!DOCTYPE html>");
+            assert_eq!(format!("{:?}", document.nth(0).unwrap()),
+                       r#"Doctype { name: "html", public_id: "", system_id: "" }"#);
         }
 
         test "Children::into_selection()" {
@@ -72,6 +72,180 @@ speculate! {
             assert!(Attr("id", ()).matches(&article));
         }
 
+        test "NsName() / NsAttr()" {
+            let document = Document::from("<title>a</title>\
+<svg><title>b</title><a xlink:href='#c'>c</a><a href='#d'>d</a></svg>\
+<math><mi>e</mi></math>");
+            let texts = |predicate: &dyn Predicate| {
+                document
+                    .find(|node: &node::Node| predicate.matches(node))
+                    .map(|node| node.text())
+                    .collect::<Vec<_>>()
+            };
+            let html = "http://www.w3.org/1999/xhtml";
+            let svg = "http://www.w3.org/2000/svg";
+            let xlink = "http://www.w3.org/1999/xlink";
+            assert_eq!(texts(&NsName(html, "title")), vec!["a"]);
+            assert_eq!(texts(&NsName(svg, "title")), vec!["b"]);
+            assert_eq!(texts(&NsName(String::from("http://www.w3.org/1998/Math/MathML"), "mi")), vec!["e"]);
+            assert_eq!(texts(&NsName(html, "mi")), Vec::<String>::new());
+            assert_eq!(texts(&NsAttr(xlink, "href", ())), vec!["c"]);
+            assert_eq!(texts(&NsAttr("", "href", ())), vec!["d"]);
+            assert_eq!(texts(&NsAttr(xlink, "href", "#c")), vec!["c"]);
+            assert_eq!(texts(&NsAttr(xlink, "href", "#d")), Vec::<String>::new());
+            assert_eq!(texts(&NsAttr("", "HREF", IgnoreCase("#D"))), Vec::<String>::new());
+            assert_eq!(texts(&NsAttr("", "href", IgnoreCase("#D"))), vec!["d"]);
+        }
+
+        test "Attr() with IgnoreCase()" {
+            assert!(!Attr("id", "POST-0").matches(&article));
+            assert!(Attr("id", IgnoreCase("POST-0")).matches(&article));
+            assert!(!Attr("id", IgnoreCase("post")).matches(&article));
+            assert!(!Attr("id", IgnoreCase("POST-0")).matches(&html));
+        }
+
+        test "AttrPrefix()" {
+            assert!(AttrPrefix("id", "post-").matches(&article));
+            assert!(AttrPrefix("id", "post-0").matches(&article));
+            assert!(!AttrPrefix("id", "ost").matches(&article));
+            assert!(!AttrPrefix("id", "").matches(&article));
+            assert!(!AttrPrefix("id", "POST").matches(&article));
+            assert!(AttrPrefix("id", IgnoreCase("POST")).matches(&article));
+            assert!(!AttrPrefix("id", "post").matches(&html));
+        }
+
+        test "AttrSuffix()" {
+            assert!(AttrSuffix("id", "-0").matches(&article));
+            assert!(!AttrSuffix("id", "post").matches(&article));
+            assert!(!AttrSuffix("id", "").matches(&article));
+            assert!(!AttrSuffix("class", "BAR").matches(&article));
+            assert!(AttrSuffix("class", IgnoreCase("BAR")).matches(&article));
+        }
+
+        test "AttrSubstring()" {
+            assert!(AttrSubstring("class", "category").matches(&article));
+            assert!(AttrSubstring("class", "t c").matches(&article));
+            assert!(!AttrSubstring("class", "").matches(&article));
+            assert!(!AttrSubstring("class", "Foo").matches(&article));
+            assert!(AttrSubstring("class", IgnoreCase("Foo T")).matches(&article));
+            assert!(!AttrSubstring("class", "a").matches(&html));
+        }
+
+        test "AttrWord()" {
+            assert!(AttrWord("class", "post").matches(&article));
+            assert!(AttrWord("class", "tag-bar").matches(&article));
+            assert!(!AttrWord("class", "tag").matches(&article));
+            assert!(!AttrWord("class", "post category-foo").matches(&article));
+            assert!(!AttrWord("class", "").matches(&article));
+            assert!(AttrWord("class", IgnoreCase("Tag-Bar")).matches(&article));
+            assert!(!AttrWord("class", "post").matches(&html));
+        }
+
+        test "AttrDash()" {
+            assert!(AttrDash("id", "post").matches(&article));
+            assert!(AttrDash("id", "post-0").matches(&article));
+            assert!(!AttrDash("id", "pos").matches(&article));
+            assert!(!AttrDash("id", "post-").matches(&article));
+            assert!(AttrDash("id", IgnoreCase("POST")).matches(&article));
+            assert!(!AttrDash("id", "post").matches(&html));
+        }
+
+        #[cfg(feature = "regex")]
+        test "AttrRegex()" {
+            use regex::Regex;
+
+            assert!(AttrRegex("id", Regex::new(r"^post-\d+$").unwrap()).matches(&article));
+            assert!(!AttrRegex("id", Regex::new(r"^\d+$").unwrap()).matches(&article));
+            assert!(!AttrRegex("id", Regex::new("").unwrap()).matches(&html));
+            assert_eq!(AttrRegex("id", Regex::new(r"^\d+$").unwrap()).describe().to_string(),
+                       r#"[id=~"^\\d+$"]"#);
+        }
+
+        #[cfg(feature = "regex")]
+        test "TextRegex()" {
+            use regex::Regex;
+
+            assert!(TextRegex(Regex::new("^fo+$").unwrap()).matches(&foo));
+            assert!(!TextRegex(Regex::new("bar").unwrap()).matches(&foo));
+            assert!(!TextRegex(Regex::new("").unwrap()).matches(&article));
+            assert!(!TextRegex(Regex::new("").unwrap()).matches(&comment));
+        }
+
+        #[cfg(feature = "regex")]
+        test "NameRegex()" {
+            use regex::Regex;
+
+            assert!(NameRegex(Regex::new("^h(tml|ead)$").unwrap()).matches(&html));
+            assert!(NameRegex(Regex::new("^h(tml|ead)$").unwrap()).matches(&head));
+            assert!(!NameRegex(Regex::new("^h(tml|ead)$").unwrap()).matches(&body));
+            assert!(!NameRegex(Regex::new("").unwrap()).matches(&foo));
+        }
+
+        test "ContainsText()" {
+            let document = Document::from("<dl><dt>  Unit\n <b>Price</b>:</dt><dd>ÉTÉ 1</dd></dl>");
+            let dt = document.find(Name("dt")).next().unwrap();
+            let dd = document.find(Name("dd")).next().unwrap();
+
+            assert!(ContainsText("Price:").matches(&dt));
+            assert!(ContainsText("Unit").matches(&dt));
+            assert!(ContainsText("").matches(&dt));
+            assert!(!ContainsText("price").matches(&dt));
+            assert!(!ContainsText("Unit Price").matches(&dt));
+            assert!(ContainsText(IgnoreCase("PRICE:")).matches(&dt));
+            assert!(ContainsText(NormalizeWhitespace("Unit Price:")).matches(&dt));
+            assert!(ContainsText(NormalizeWhitespace("  t   P")).matches(&dt));
+            assert!(ContainsText(IgnoreCase(NormalizeWhitespace("unit price"))).matches(&dt));
+            assert!(ContainsText(IgnoreCase("été")).matches(&dd));
+            assert!(ContainsText("Price").matches(&dt.find(Text).nth(1).unwrap()));
+            assert!(ContainsText("Price").matches(&document.nth(0).unwrap()));
+            assert!(!ContainsText("Price").matches(&dd));
+
+            // Partial matches overlapping the actual one.
+            let document = Document::from("<p>aab<b>aaab</b>abaabab</p>");
+            let p = document.find(Name("p")).next().unwrap();
+            assert!(ContainsText("aaab").matches(&p));
+            assert!(ContainsText("baaa").matches(&p));
+            assert!(ContainsText("abab").matches(&p));
+            assert!(ContainsText("aabaaab").matches(&p));
+            assert!(!ContainsText("aaaa").matches(&p));
+            assert!(!ContainsText("babb").matches(&p));
+
+            // Needles too long to search for without allocating.
+            let text = "ab".repeat(40) + "c";
+            let document = Document::from(format!("<p>a{}</p>", text).as_str());
+            let p = document.find(Name("p")).next().unwrap();
+            assert!(ContainsText(text.as_str()).matches(&p));
+            assert!(!ContainsText(text.replace('c', "d").as_str()).matches(&p));
+        }
+
+        test "OwnTextContains()" {
+            let document = Document::from("<dl><dt>  Unit\n <b>Price</b>:</dt><dd>ÉTÉ 1</dd></dl>");
+            let dt = document.find(Name("dt")).next().unwrap();
+
+            assert!(OwnTextContains("Unit").matches(&dt));
+            assert!(OwnTextContains(" :").matches(&dt));
+            assert!(!OwnTextContains("Price").matches(&dt));
+            assert!(OwnTextContains(NormalizeWhitespace("Unit :")).matches(&dt));
+            assert!(OwnTextContains("Price").matches(&dt.find(Name("b")).next().unwrap()));
+            assert!(!OwnTextContains("Price").matches(&document.nth(0).unwrap()));
+        }
+
+        test "TextEquals()" {
+            let document = Document::from("<dl><dt>  Unit\n <b>Price</b>:</dt><dd>ÉTÉ 1</dd></dl>");
+            let dt = document.find(Name("dt")).next().unwrap();
+            let dd = document.find(Name("dd")).next().unwrap();
+
+            assert!(TextEquals("ÉTÉ 1").matches(&dd));
+            assert!(!TextEquals("ÉTÉ").matches(&dd));
+            assert!(!TextEquals("été 1").matches(&dd));
+            assert!(TextEquals(IgnoreCase("été 1")).matches(&dd));
+            assert!(!TextEquals("Unit Price:").matches(&dt));
+            assert!(TextEquals(NormalizeWhitespace("Unit Price:")).matches(&dt));
+            assert!(TextEquals(NormalizeWhitespace(" Unit  Price: ")).matches(&dt));
+            assert!(TextEquals(IgnoreCase(NormalizeWhitespace("unit price:"))).matches(&dt));
+            assert!(TextEquals("").matches(&comment));
+        }
+
         test "Fn(&Node) -> bool" {
             let f = |node: &node::Node| node.name() == Some("html");
             assert!(f.matches(&html));
@@ -105,6 +279,67 @@ speculate! {
             assert!(super::Comment.matches(&comment));
         }
 
+        test "Doctype" {
+            let document = Document::from("<!doctype html><html>");
+            assert!(super::Doctype.matches(&document.nth(0).unwrap()));
+            assert!(!super::Doctype.matches(&document.nth(1).unwrap()));
+            assert!(!super::Doctype.matches(&html));
+            assert!(!super::Doctype.matches(&comment));
+        }
+
+        test "ProcessingInstruction" {
+            let document = Document::from(vec![node::Raw {
+                index: 0,
+                parent: None,
+                prev: None,
+                next: None,
+                first_child: None,
+                last_child: None,
+                data: node::Data::ProcessingInstruction {
+                    target: "xml-stylesheet".into(),
+                    data: "href='a.css'".into(),
+                },
+            }]);
+            let pi = document.nth(0).unwrap();
+            assert!(super::ProcessingInstruction.matches(&pi));
+            assert!(!super::ProcessingInstruction.matches(&html));
+            assert!(!super::ProcessingInstruction.matches(&comment));
+            assert!(!super::Comment.matches(&pi));
+            assert_eq!(pi.html(), "<?xml-stylesheet href='a.css'>");
+        }
+
+        test "structural predicates" {
+            let document = Document::from("<table><tr><th>h</th>\
+<td>1</td> <!--x--><td>2</td><td></td><td>4</td></tr></table>");
+            let texts = |predicate: &dyn Predicate| {
+                document
+                    .find(|node: &node::Node| node.is(Element) && predicate.matches(node))
+                    .filter(|node| node.parent().is_some_and(|parent| parent.is(Name("tr"))))
+                    .map(|node| node.text())
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(texts(&NthChild(0, 3)), vec!["2"]);
+            assert_eq!(texts(&NthChild(2, 0)), vec!["1", ""]);
+            assert_eq!(texts(&NthChild(2, 1)), vec!["h", "2", "4"]);
+            assert_eq!(texts(&NthChild(-1, 2)), vec!["h", "1"]);
+            assert_eq!(texts(&NthLastChild(0, 2)), vec![""]);
+            assert_eq!(texts(&NthOfType(0, 3)), vec![""]);
+            assert_eq!(texts(&Name("td").and(NthOfType(1, 2))), vec!["2", "", "4"]);
+            assert_eq!(texts(&NthLastOfType(0, 1)), vec!["h", "4"]);
+            assert_eq!(texts(&FirstChild), vec!["h"]);
+            assert_eq!(texts(&LastChild), vec!["4"]);
+            assert_eq!(texts(&Empty), vec![""]);
+
+            let table = document.find(Name("table")).next().unwrap();
+            assert!(OnlyChild.matches(&table.first_child().unwrap()));
+            assert!(!OnlyChild.matches(&document.find(Name("th")).next().unwrap()));
+            assert!(!FirstChild.matches(&document.find(Text).next().unwrap()));
+            assert!(!Empty.matches(&table));
+            assert!(Root.matches(&document.nth(0).unwrap()));
+            assert!(!Root.matches(&table));
+        }
+
         test "Or()" {
             let html_or_head = Or(Name("html"), Name("head"));
             assert!(html_or_head.matches(&html));
@@ -123,6 +358,42 @@ speculate! {
             assert!(!article_and_post_0.matches(&foo));
         }
 
+        test "AnyOf() / AllOf()" {
+            let classes = ["post", "nope", "tag-bar"];
+            let any = AnyOf(vec![Class("nope"), Class("tag-bar")]);
+            assert!(any.matches(&article));
+            assert!(!any.matches(&html));
+            assert!(AnyOf(&classes.map(Class)[..]).matches(&article));
+            assert!(AnyOf([Name("a"), Name("article")]).matches(&article));
+            assert!(!AnyOf(Vec::<Name<&str>>::new()).matches(&article));
+
+            assert!(!AllOf(classes.map(Class)).matches(&article));
+            assert!(AllOf(vec![Class("post"), Class("tag-bar")]).matches(&article));
+            assert!(AllOf(Vec::<Name<&str>>::new()).matches(&article));
+
+            let blocklist = "post-1\npost-0\n".lines().map(|id| Attr("id", id)).collect::<AnyOf<_>>();
+            assert!(blocklist.matches(&article));
+            assert!(!blocklist.matches(&a));
+            let rules: AllOf<Vec<BoxedPredicate>> =
+                vec![Element.boxed(), Class("post").boxed()].into_iter().collect();
+            assert!(rules.matches(&article));
+            assert!(!rules.matches(&foo));
+
+            // Short-circuits on the first match / mismatch.
+            let calls = std::cell::Cell::new(0);
+            let calls = &calls;
+            let count = |matched: bool| {
+                move |_: &node::Node| {
+                    calls.set(calls.get() + 1);
+                    matched
+                }
+            };
+            assert!(AnyOf([count(false), count(true), count(true)]).matches(&article));
+            assert_eq!(calls.get(), 2);
+            assert!(!AllOf([count(true), count(false), count(false)]).matches(&article));
+            assert_eq!(calls.get(), 4);
+        }
+
         test "Child()" {
             let html_article = Child(Name("html"), Name("article"));
             assert!(!html_article.matches(&html));
@@ -137,6 +408,7 @@ speculate! {
             let check = |parent: &str, child: &str, matching: Option<usize>| {
                 let selector = Descendant(Class(parent), Class(child));
                 for node in &[a, b, c, d] {
+                    #[allow(clippy::unnecessary_map_or)]
                     let expected = matching.map_or(false, |index| node.index() == index);
                     assert_eq!(selector.matches(node), expected);
                 }
@@ -160,6 +432,78 @@ speculate! {
             check("d", "d", None);
         }
 
+        test "AdjacentSibling()" {
+            let document = Document::from("<dl><dt>Name</dt><dd>a</dd>\
+<dt>Price</dt> <!--x--> <dd>1</dd><dd>2</dd><dt>Size</dt><dd>3</dd></dl>");
+            let price = |node: &node::Node| node.is(Name("dt")) && node.text() == "Price";
+
+            let dd = document
+                .find(AdjacentSibling(price, Name("dd")))
+                .map(|node| node.text())
+                .collect::<Vec<_>>();
+            assert_eq!(dd, vec!["1"]);
+
+            let dd = document.find(Name("dd")).into_selection();
+            assert_eq!(dd.filter(Name("dt").adjacent_sibling(Name("dd"))).len(), 3);
+            assert_eq!(dd.filter(Name("dd").adjacent_sibling(Any)).len(), 1);
+            assert!(!Any.adjacent_sibling(Any).matches(&document.find(Name("dt")).next().unwrap()));
+        }
+
+        test "GeneralSibling()" {
+            let document = Document::from("<dl><dt>Name</dt><dd>a</dd>\
+<dt>Price</dt> <!--x--> <dd>1</dd><dd>2</dd><dt>Size</dt><dd>3</dd></dl>");
+            let price = |node: &node::Node| node.is(Name("dt")) && node.text() == "Price";
+
+            let dd = document
+                .find(GeneralSibling(price, Name("dd")))
+                .map(|node| node.text())
+                .collect::<Vec<_>>();
+            assert_eq!(dd, vec!["1", "2", "3"]);
+
+            let dd = document.find(Name("dd")).into_selection();
+            assert_eq!(dd.filter(Name("dd").general_sibling(Name("dd"))).len(), 3);
+            assert_eq!(dd.filter(Name("dl").general_sibling(Any)).len(), 0);
+            assert!(!Any.general_sibling(Any).matches(&document.find(Name("dt")).next().unwrap()));
+        }
+
+        test "Has()" {
+            let document = Document::from("<div class=card><p><span class=sold-out></span></p></div>\
+<div class=card><span class=badge></span></div>");
+            let cards = document
+                .find(Class("card").has(Class("sold-out")))
+                .collect::<Vec<_>>();
+            assert_eq!(cards.len(), 1);
+            assert_eq!(cards[0], document.find(Class("card")).next().unwrap());
+
+            assert!(Has(Class("c")).matches(&a));
+            assert!(Has(Class("d")).matches(&a));
+            assert!(!Has(Class("a")).matches(&a));
+            assert!(!Has(Class("d")).matches(&d));
+            assert!(Has(Text).matches(&article));
+        }
+
+        test "HasChild()" {
+            assert!(HasChild(Class("b")).matches(&a));
+            assert!(!HasChild(Class("c")).matches(&a));
+            assert!(HasChild(Text).matches(&article));
+            assert!(!HasChild(Any).matches(&d));
+        }
+
+        test "HasAdjacentSibling() / HasGeneralSibling()" {
+            let document = Document::from("<dt>a</dt><dd>1</dd><dt>b</dt> <!--x--> <dt>c</dt><dd>2</dd>");
+            let dts = |predicate: &dyn Predicate| {
+                document
+                    .find(|node: &node::Node| node.is(Name("dt")) && predicate.matches(node))
+                    .map(|node| node.text())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(dts(&HasAdjacentSibling(Name("dd"))), vec!["a", "c"]);
+            assert_eq!(dts(&HasAdjacentSibling(Name("dt"))), vec!["b"]);
+            assert_eq!(dts(&HasGeneralSibling(Name("dd"))), vec!["a", "b", "c"]);
+            assert_eq!(dts(&HasGeneralSibling(Name("dt"))), vec!["a", "b"]);
+            assert_eq!(dts(&HasGeneralSibling(Comment)), Vec::<String>::new());
+        }
+
         // https://github.com/utkarshkukreti/select.rs/issues/35
         test "Box<Predicate>" {
             let post_0: Box<dyn Predicate> = Box::new(Attr("id", "post-0"));
@@ -171,5 +515,93 @@ speculate! {
             assert!(not_html.matches(&head));
             assert!(not_html.matches(&article));
         }
+
+        test "owned names and values" {
+            use std::borrow::Cow;
+            use std::sync::Arc;
+
+            assert!(Name(String::from("article")).matches(&article));
+            assert!(Name(Cow::from("article")).matches(&article));
+            assert!(!Name(Arc::<str>::from("html")).matches(&article));
+            assert!(Class(String::from("tag-bar")).matches(&article));
+            assert!(Class(Arc::<str>::from("tag-bar")).matches(&article));
+            assert!(Attr(String::from("id"), ()).matches(&article));
+            assert!(Attr(String::from("id"), String::from("post-0")).matches(&article));
+            assert!(Attr(Cow::from("id"), Cow::from("POST-0")).not().matches(&article));
+            assert!(Attr(Arc::<str>::from("id"), IgnoreCase(Arc::<str>::from("POST-0"))).matches(&article));
+            assert!(AttrPrefix(String::from("class"), String::from("post ")).matches(&article));
+            assert!(ContainsText(String::from("fo")).matches(&article));
+        }
+
+        test "BoxedPredicate" {
+            let rules: Vec<(&str, BoxedPredicate)> = vec![
+                ("post", Name(String::from("article")).boxed()),
+                ("comment", Box::new(Comment)),
+                ("nested", Class("a").descendant(Class("d")).boxed()),
+            ];
+            let matched = |node: &node::Node| {
+                rules
+                    .iter()
+                    .filter(|(_, predicate)| predicate.matches(node))
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(matched(&article), vec!["post"]);
+            assert_eq!(matched(&comment), vec!["comment"]);
+            assert_eq!(matched(&d), vec!["nested"]);
+            assert_eq!(matched(&html), Vec::<&str>::new());
+
+            let (_, post) = rules.into_iter().next().unwrap();
+            let post = post.and(Attr("id", "post-0"));
+            assert!(post.matches(&article));
+            assert!(std::thread::spawn(move || post.or(Name("html")).boxed()).join().is_ok());
+        }
+
+        test "describe()" {
+            let describe = |predicate: &dyn Predicate| predicate.to_string();
+            assert_eq!(Attr("id", "hmenus").descendant(Name("a")).describe().to_string(), "#hmenus a");
+            assert_eq!(describe(&Any), ":any-node");
+            assert_eq!(describe(&Element), "*");
+            assert_eq!(describe(&Name("div").and(Class("a")).and(Attr("href", ()))), "div.a[href]");
+            assert_eq!(describe(&Class("a").and(Name("div"))), ".a:is(div)");
+            assert_eq!(describe(&Attr("id", "1x")), "[id=\"1x\"]");
+            assert_eq!(describe(&Attr("data-x", IgnoreCase("a\"b"))), "[data-x=\"a\\\"b\" i]");
+            assert_eq!(describe(&AttrPrefix("href", "/q")), "[href^=\"/q\"]");
+            assert_eq!(describe(&AttrWord("class", "a")), "[class~=\"a\"]");
+            assert_eq!(describe(&Class("a:b")), ".a\\:b");
+            assert_eq!(describe(&NsName("http://www.w3.org/2000/svg", "title")),
+                       "{http://www.w3.org/2000/svg}title");
+            assert_eq!(describe(&NsAttr("http://www.w3.org/1999/xlink", "href", ())),
+                       "[{http://www.w3.org/1999/xlink}href]");
+            assert_eq!(describe(&Name("ul").child(Name("li").or(Name("p")))), "ul > :is(li, p)");
+            assert_eq!(describe(&Name("ul").child(Name("li").descendant(Name("a")))), "ul > :is(li a)");
+            assert_eq!(describe(&Name("ul").descendant(Name("li")).child(Name("a"))), "ul li > a");
+            assert_eq!(describe(&Name("dt").adjacent_sibling(Name("dd"))), "dt + dd");
+            assert_eq!(describe(&Name("dt").general_sibling(Name("dd"))), "dt ~ dd");
+            assert_eq!(describe(&Name("a").or(Name("b")).not()), ":not(a, b)");
+            assert_eq!(describe(&Name("a").has(Class("b"))), "a:has(.b)");
+            assert_eq!(describe(&HasChild(Name("a").descendant(Name("b")))), ":has(> :is(a b))");
+            assert_eq!(describe(&HasAdjacentSibling(Name("dd"))), ":has(+ dd)");
+            assert_eq!(describe(&Name("li").and(NthChild(2, 1))), "li:nth-child(2n+1)");
+            assert_eq!(describe(&NthLastOfType(-1, 3)), ":nth-last-of-type(-n+3)");
+            assert_eq!(describe(&NthOfType(0, 2)), ":nth-of-type(2)");
+            assert_eq!(describe(&FirstChild.or(Empty).or(Root)), ":first-child, :empty, :root");
+            assert_eq!(describe(&Text.or(Comment)), ":text, :comment");
+            assert_eq!(describe(&ContainsText("a")), ":contains(\"a\")");
+            assert_eq!(describe(&TextEquals(IgnoreCase(NormalizeWhitespace("a  b")))),
+                       ":text-equals(normalize-space(\"a  b\") i)");
+            assert_eq!(describe(&AnyOf(vec![Class("a"), Class("b")])), ".a, .b");
+            assert_eq!(describe(&Name("p").child(AnyOf(vec![Class("a"), Class("b")]))), "p > :is(.a, .b)");
+            assert_eq!(describe(&AnyOf(Vec::<Name<&str>>::new())), ":is()");
+            assert_eq!(describe(&AllOf([Name("p"), Name("q")])), "p:is(q)");
+            assert_eq!(describe(&AllOf(Vec::<Name<&str>>::new())), ":any-node");
+
+            // Opaque closures and boxed predicates.
+            assert_eq!(describe(&|_: &node::Node| true), "<fn>");
+            assert_eq!(describe(&Name("a").and(|_: &node::Node| true)), "a:is(<fn>)");
+            let boxed: BoxedPredicate = Class("a").descendant(Name("b")).boxed();
+            assert_eq!(boxed.to_string(), ".a b");
+            assert_eq!(boxed.describe().to_string(), ".a b");
+        }
     }
 }
@@ -27,7 +27,7 @@ speculate! {
             let document = Document::from(include_str!("fixtures/struct.Vec.html"));
             let all = document.find(Any).into_selection();
 
-            assert_eq!(all.filter(Any).len(), 11446);
+            assert_eq!(all.filter(Any).len(), 11447);
 
             let divs = all.filter(Name("div"));
             assert_eq!(divs.len(), 208);
@@ -63,6 +63,42 @@ speculate! {
             };
         }
 
+        test "Selection::find() matches searching each node" {
+            use select::predicate::*;
+
+            fn naive(selection: &Selection, name: &str) -> Vec<usize> {
+                let mut indices = selection
+                    .iter()
+                    .flat_map(|node| node.find(Name(name)).map(|node| node.index()).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                indices.sort();
+                indices.dedup();
+                indices
+            }
+            let indices = |selection: &Selection| {
+                selection.iter().map(|node| node.index()).collect::<Vec<_>>()
+            };
+
+            let document = Document::from(include_str!("fixtures/struct.Vec.html"));
+            for predicate in [Name("div"), Name("span"), Name("body"), Name("html")] {
+                let selection = document.find(predicate).into_selection();
+                for name in ["a", "span", "div"] {
+                    assert_eq!(indices(&selection.find(Name(name))), naive(&selection, name));
+                }
+            }
+
+            // Selected nodes inside the skipped contents of a template.
+            let document = Document::from("<div id=o><template><section><span><p>b</p></span></section>\
+</template><p>c</p></div>");
+            let div = document.find(Attr("id", "o")).into_selection();
+            let section = div.find(Name("template")).children();
+            assert_eq!(section.first().unwrap().name(), Some("section"));
+            assert_eq!(div.find(Name("p")).texts(), vec!["c"]);
+            let selection = &div | &section;
+            assert_eq!(indices(&selection.find(Name("p"))), naive(&selection, "p"));
+            assert_eq!(selection.find(Name("p")).texts(), vec!["b", "c"]);
+        }
+
         test "Selection::parent()" {
             use select::predicate::*;
 
@@ -145,6 +181,160 @@ speculate! {
             check(&document, Name("span"));
         }
 
+        test "Selection traversals" {
+            use select::predicate::*;
+
+            let document = Document::from("<ul id=a><li id=b>1</li><li id=c class=x>2</li>\
+<li id=d><ul id=e><li id=f>3</li><li id=g class=x>4</li></ul></li><li id=h>5</li></ul>");
+            let ids = |selection: &Selection| {
+                selection.iter().filter_map(|node| node.attr("id").map(String::from)).collect::<Vec<_>>()
+            };
+            let all = |selection: &Selection| {
+                selection.iter().map(|node| node.index()).collect::<Vec<_>>()
+            };
+            let select = |id: &str| document.find(Attr("id", id)).into_selection();
+            let xs = document.find(Class("x")).into_selection();
+            let f = select("f");
+
+            assert_eq!(ids(&xs.closest(Name("ul"))), vec!["a", "e"]);
+            assert_eq!(ids(&xs.closest(Name("li"))), vec!["c", "g"]);
+            assert_eq!(ids(&f.closest(Attr("id", "d"))), vec!["d"]);
+            assert!(f.closest(Name("table")).is_empty());
+
+            assert_eq!(ids(&f.parents_until(Attr("id", "a"))), vec!["d", "e"]);
+            assert_eq!(ids(&f.parents_until(Name("ul"))), Vec::<&str>::new());
+            assert_eq!(f.parents_until(Name("table")), f.parents());
+
+            assert_eq!(ids(&select("b").next_all()), vec!["c", "d", "h"]);
+            assert_eq!(ids(&xs.next_all()), vec!["d", "h"]);
+            assert_eq!(ids(&xs.prev_all()), vec!["b", "f"]);
+            assert_eq!(ids(&select("b").next_until(Attr("id", "h"))), vec!["c", "d"]);
+            assert_eq!(ids(&select("h").prev_until(Class("x"))), vec!["d"]);
+
+            assert_eq!(ids(&select("c").siblings()), vec!["b", "d", "h"]);
+            assert_eq!(ids(&xs.siblings()), vec!["b", "d", "f", "h"]);
+            assert_eq!(ids(&(&select("b") | &select("c")).siblings()), vec!["b", "c", "d", "h"]);
+            assert_eq!(ids(&(&select("b") | &select("h")).siblings()), vec!["b", "c", "d", "h"]);
+
+            let fragment = Document::from_fragment("<li id=a>a</li><li id=b>b</li>c<li id=d>", "ul");
+            let b = fragment.find(Attr("id", "b")).into_selection();
+            assert!(b.parent().is_empty());
+            assert_eq!(b.siblings().iter().map(|node| node.index()).collect::<Vec<_>>(),
+                       vec![0, 4, 5]);
+            let page = Document::from("<!DOCTYPE html><html>");
+            let html = page.find(Name("html")).into_selection();
+            assert_eq!(html.siblings().iter().map(|node| node.index()).collect::<Vec<_>>(),
+                       vec![0]);
+
+            let lis = document.find(Name("li")).into_selection();
+            assert_eq!(ids(&lis.not(Class("x"))), vec!["b", "d", "f", "h"]);
+            assert_eq!(all(&lis.not(Class("x"))), all(&lis.filter(Not(Class("x")))));
+            assert_eq!(ids(&lis.has(Class("x"))), vec!["d"]);
+            assert_eq!(ids(&lis.has(Name("ul"))), vec!["d"]);
+            assert!(lis.is(Class("x")));
+            assert!(!lis.is(Name("ul")));
+            assert!(!xs.filter(Name("ul")).is(Any));
+
+            // Traversals return selections in document order.
+            assert_eq!(ids(&xs.parent().children().not(Class("x"))), vec!["b", "d", "f", "h"]);
+            assert_eq!(ids(&f.next_all().closest(Name("ul")).parents_until(Any)), Vec::<&str>::new());
+            assert_eq!(ids(&f.next_all().closest(Name("li")).siblings()), vec!["f"]);
+        }
+
+        test "Selection positional access" {
+            use select::predicate::*;
+
+            let document = Document::from("<ul><li>0</li><li>1</li><li>2</li><li>3</li><li>4</li></ul>");
+            let lis = document.find(Name("li")).into_selection();
+            let texts = |iter: &mut dyn Iterator<Item = select::node::Node>| {
+                iter.map(|node| node.text()).collect::<Vec<_>>()
+            };
+            let text = |node: Option<select::node::Node>| node.map(|node| node.text());
+
+            assert_eq!(text(lis.nth(1)), Some("1".into()));
+            assert_eq!(text(lis.nth(5)), None);
+            assert_eq!(text(lis.at(0)), Some("0".into()));
+            assert_eq!(text(lis.at(-1)), Some("4".into()));
+            assert_eq!(text(lis.at(-5)), Some("0".into()));
+            assert_eq!(text(lis.at(-6)), None);
+            assert_eq!(text(lis.last()), Some("4".into()));
+
+            assert_eq!(texts(&mut lis.slice(1..3).iter()), vec!["1", "2"]);
+            assert_eq!(texts(&mut lis.slice(lis.len() - 3..).iter()), vec!["2", "3", "4"]);
+            assert_eq!(texts(&mut lis.slice(..=1).iter()), vec!["0", "1"]);
+            assert_eq!(lis.slice(..), lis);
+            assert!(lis.slice(4..9).len() == 1 && lis.slice(7..).is_empty());
+            assert!(lis.slice(3..3).is_empty());
+
+            assert_eq!(texts(&mut lis.rev()), vec!["4", "3", "2", "1", "0"]);
+            let mut iter = lis.iter();
+            assert_eq!(iter.len(), 5);
+            assert_eq!(text(iter.next_back()), Some("4".into()));
+            assert_eq!(text(iter.next()), Some("0".into()));
+            assert_eq!(iter.len(), 3);
+            assert_eq!(text(iter.next_back()), Some("3".into()));
+            assert_eq!(texts(&mut iter.clone()), vec!["1", "2"]);
+            assert_eq!(text(iter.next_back()), Some("2".into()));
+            assert_eq!(text(iter.next()), Some("1".into()));
+            assert_eq!(iter.len(), 0);
+            assert!(iter.next().is_none() && iter.next_back().is_none());
+
+            let empty = document.find(Name("p")).into_selection();
+            assert!(empty.rev().next().is_none() && empty.at(-1).is_none());
+        }
+
+        test "Selection extraction" {
+            use select::predicate::*;
+
+            let document = Document::from("<p><a href=/a>A</a> <a>B <b>b</b></a> <a href=/c></a></p>");
+            let links = document.find(Name("a")).into_selection();
+
+            assert_eq!(links.texts(), vec!["A", "B b", ""]);
+            assert_eq!(links.attrs("href"), vec!["/a", "/c"]);
+            assert!(links.attrs("id").is_empty());
+            assert_eq!(links.text(", "), "A, B b, ");
+            assert_eq!(links.text(""), document.nth(0).unwrap().find(Name("a")).map(|node| node.text()).collect::<String>());
+            assert_eq!(links.html(), "<a href=\"/a\">A</a><a>B <b>b</b></a><a href=\"/c\"></a>");
+
+            let empty = links.filter(Name("p"));
+            assert!(empty.texts().is_empty());
+            assert_eq!(empty.text("\n"), "");
+            assert_eq!(empty.html(), "");
+        }
+
+        test "Selection set operations" {
+            use select::predicate::*;
+
+            let document = Document::from("<p class=price>1</p><div class=ad><p class=price>2</p></div>\
+<p class=price>3</p><p>4</p>");
+            let texts = |selection: &Selection| {
+                selection.iter().map(|node| node.text()).collect::<Vec<_>>()
+            };
+            let prices = document.find(Class("price")).into_selection();
+            let ads = document.find(Class("ad")).into_selection().find(Any);
+            let paragraphs = document.find(Name("p")).into_selection();
+
+            assert_eq!(texts(&prices.difference(&ads)), vec!["1", "3"]);
+            assert_eq!(texts(&(&prices - &ads)), vec!["1", "3"]);
+            assert_eq!(texts(&prices.intersection(&ads)), vec!["2"]);
+            assert_eq!(texts(&(&prices & &ads)), vec!["2"]);
+            assert_eq!(texts(&paragraphs.union(&ads)), vec!["1", "2", "2", "3", "4"]);
+            assert_eq!(&paragraphs | &ads, &ads | &paragraphs);
+            assert_eq!(texts(&paragraphs.symmetric_difference(&prices)), vec!["4"]);
+            assert_eq!(texts(&(paragraphs.clone() ^ prices.clone())), vec!["4"]);
+            assert_eq!((prices.clone() - paragraphs.clone()).len(), 0);
+            assert_eq!((prices.clone() | paragraphs.clone()).len(), 4);
+            assert_eq!(prices.clone() & paragraphs, prices);
+        }
+
+        #[should_panic(expected = "different documents")]
+        test "Selection set operations with different documents" {
+            let a = Document::from("<p>");
+            let b = Document::from("<p>");
+            let _ = a.find(select::predicate::Any).into_selection()
+                | b.find(select::predicate::Any).into_selection();
+        }
+
         test "Iter (lifetimes)" {
             let document = Document::from("<html><head></head><body>\
 <article id='post-0' class='post category-foo tag-bar'></article>\
@@ -0,0 +1,204 @@
+#![allow(unused_variables)]
+
+pub use select::document::Document;
+pub use select::predicate::*;
+pub use select::selector::*;
+
+use speculate::speculate;
+
+speculate! {
+    describe "selector" {
+        before {
+            let document = Document::from("<html><head></head><body>\
+<div class='question-summary' id='q-1'><a href='/questions/1'>One</a><a href='/users/1'>User</a></div>\
+<div class='question-summary featured'><span><a href='/questions/2'>Two</a></span></div>\
+<ul lang='en-US'><li>a</li><li class=x>b</li><li>c</li><li></li><!--d--><p>e</p></ul>\
+<form><input type=checkbox checked><input disabled><option selected>o</option></form>\
+</body></html>");
+
+            let select = |selector: &str| {
+                document
+                    .select(selector)
+                    .unwrap()
+                    .map(|node| node.index())
+                    .collect::<Vec<_>>()
+            };
+            let find = |predicate: &dyn Predicate| {
+                document
+                    .find(|node: &select::node::Node| predicate.matches(node))
+                    .map(|node| node.index())
+                    .collect::<Vec<_>>()
+            };
+        }
+
+        test "Document::select()" {
+            let links = document
+                .select("div.question-summary > a[href^='/q']")
+                .unwrap()
+                .map(|node| node.text())
+                .collect::<Vec<_>>();
+            assert_eq!(links, vec!["One"]);
+        }
+
+        test "type, universal, id and class selectors" {
+            assert_eq!(select("div"), find(&Name("div")));
+            assert_eq!(select("DIV"), find(&Name("div")));
+            assert_eq!(select("*"), find(&Element));
+            assert_eq!(select("*|li"), find(&Name("li")));
+            assert_eq!(select("#q-1"), find(&Attr("id", "q-1")));
+            assert_eq!(select(".question-summary.featured"),
+                       find(&Class("question-summary").and(Class("featured"))));
+            assert_eq!(select("div, li"), find(&Name("div").or(Name("li"))));
+        }
+
+        test "attribute selectors" {
+            assert_eq!(select("[href]"), find(&Attr("href", ())));
+            assert_eq!(select("[href='/users/1']"), find(&Attr("href", "/users/1")));
+            assert_eq!(select("[class~=featured]").len(), 1);
+            assert_eq!(select("[class~='']").len(), 0);
+            assert_eq!(select("[lang|=en]").len(), 1);
+            assert_eq!(select("[lang|=e]").len(), 0);
+            assert_eq!(select("a[href^='/questions/']").len(), 2);
+            assert_eq!(select("a[href$=\"2\"]").len(), 1);
+            assert_eq!(select("a[href*=users]").len(), 1);
+            assert_eq!(select("a[href*='']").len(), 0);
+            assert_eq!(select("[HREF]"), find(&Attr("href", ())));
+            assert_eq!(select("a[href^='/questions/']"), find(&AttrPrefix("href", "/questions/")));
+        }
+
+        test "attribute selector flags" {
+            assert_eq!(select("[class~=FEATURED]").len(), 0);
+            assert_eq!(select("[class~=FEATURED i]"), find(&AttrWord("class", IgnoreCase("featured"))));
+            assert_eq!(select("[class~=FEATURED I]").len(), 1);
+            assert_eq!(select("[class~=featured s]").len(), 1);
+            assert_eq!(select("[class~=FEATURED s]").len(), 0);
+            assert_eq!(select("a[href^='/Q' i]").len(), 2);
+            assert_eq!(select("[lang|=EN i]").len(), 1);
+            assert!(Selector::parse("[lang=en x]").is_err());
+        }
+
+        test "combinators" {
+            assert_eq!(select("div a"), find(&Name("div").descendant(Name("a"))));
+            assert_eq!(select("div > a"), find(&Name("div").child(Name("a"))));
+            assert_eq!(select("div>a"), find(&Name("div").child(Name("a"))));
+            assert_eq!(select("li + li").len(), 3);
+            assert_eq!(select("li.x + li").len(), 1);
+            assert_eq!(select("li.x ~ li").len(), 2);
+            assert_eq!(select("li ~ p").len(), 1);
+            assert_eq!(select("li + p").len(), 1);
+        }
+
+        test "struct.Vec.html" {
+            let document = Document::from(include_str!("fixtures/struct.Vec.html"));
+            let main = document.find(Attr("id", "main")).next().unwrap();
+            assert_eq!(document.select("div").unwrap().count(), 208);
+            assert_eq!(document.select(".struct").unwrap().count(), 168);
+            assert_eq!(document.select("#main").unwrap().count(), 1);
+            assert_eq!(main.select("div").unwrap().count(), 204);
+            assert_eq!(main.select("div > div span > span").unwrap().count(), 3);
+        }
+
+        test "structural pseudo-classes" {
+            let texts = |selector: &str| {
+                document
+                    .select(selector)
+                    .unwrap()
+                    .map(|node| node.text())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(texts("li:first-child"), vec!["a"]);
+            assert_eq!(texts("li:last-child"), Vec::<String>::new());
+            assert_eq!(texts("ul > :last-child"), vec!["e"]);
+            assert_eq!(texts("li:nth-child(2)"), vec!["b"]);
+            assert_eq!(texts("li:nth-child(odd)"), vec!["a", "c"]);
+            assert_eq!(texts("li:nth-child(2n)"), vec!["b", ""]);
+            assert_eq!(texts("li:nth-child(-n + 2)"), vec!["a", "b"]);
+            assert_eq!(texts("li:nth-last-child(2)"), vec![""]);
+            assert_eq!(texts("li:nth-of-type(3)"), vec!["c"]);
+            assert_eq!(texts("li:last-of-type"), vec![""]);
+            assert_eq!(texts("ul :nth-last-of-type(1)"), vec!["", "e"]);
+            assert_eq!(texts("p:only-of-type"), vec!["e"]);
+            assert_eq!(texts("span > a:only-child"), vec!["Two"]);
+            assert_eq!(texts("li:empty"), vec![""]);
+            assert_eq!(select(":root"), vec![0]);
+            assert_eq!(texts("li:not(.x)"), vec!["a", "c", ""]);
+            assert_eq!(texts("ul > :not(li)"), vec!["e"]);
+        }
+
+        test "other pseudo-classes" {
+            assert_eq!(select("a:link").len(), 3);
+            assert_eq!(select("a:hover").len(), 0);
+            assert_eq!(select("li:lang(en)").len(), 4);
+            assert_eq!(select("li:lang(e)").len(), 0);
+            assert_eq!(select(":checked").len(), 2);
+            assert_eq!(select(":disabled").len(), 1);
+            assert_eq!(select(":enabled").len(), 2);
+        }
+
+        test ":has()" {
+            assert_eq!(select("div:has(a)"), find(&Name("div").has(Name("a"))));
+            assert_eq!(select("div:has(> a)"), find(&Name("div").and(HasChild(Name("a")))));
+            assert_eq!(select("div:has(span a)"), select("div.featured"));
+            assert_eq!(select("div:has(> span > a)"), select("div.featured"));
+            assert_eq!(select("div:has(> span a, #nope)"), select("div.featured"));
+            assert_eq!(select("div:not(:has(span))"), select("#q-1"));
+            assert_eq!(select("*:has(a, input)"),
+                       find(&Element.and(Has(Name("a")).or(Has(Name("input"))))));
+
+            assert_eq!(select("li:has(+ li.x)"),
+                       find(&Name("li").and(HasAdjacentSibling(Name("li").and(Class("x"))))));
+            assert_eq!(select("li:has(~ p)").len(), 4);
+            assert_eq!(select("li:has(+ li + li)").len(), 2);
+            assert_eq!(select("li:has(~ li:empty)").len(), 3);
+            assert_eq!(select("ul:has(+ form input[disabled])").len(), 1);
+            assert_eq!(select("ul:has(+ form > span)").len(), 0);
+            assert_eq!(select("li:has(li)").len(), 0);
+        }
+
+        test "escapes and strings" {
+            let document = Document::from("<p class='a:b' id='1x' title='x\"y'>");
+            assert_eq!(document.select(".a\\:b").unwrap().count(), 1);
+            assert_eq!(document.select("#\\31 x").unwrap().count(), 1);
+            assert_eq!(document.select("#1x").unwrap().count(), 1);
+            assert_eq!(document.select("[title='x\"y']").unwrap().count(), 1);
+            assert_eq!(document.select("[title=\"x\\\"y\"]").unwrap().count(), 1);
+        }
+
+        test "ParseError" {
+            let error = |selector: &str| {
+                let error = Selector::parse(selector).unwrap_err();
+                (error.column(), error.message().to_string())
+            };
+
+            assert_eq!(error(""), (1, "expected selector".into()));
+            assert_eq!(error("div >"), (6, "expected selector".into()));
+            assert_eq!(error("div, "), (6, "expected selector".into()));
+            assert_eq!(error("div > > a"), (7, "expected selector, found '>'".into()));
+            assert_eq!(error("a[href"), (7, "expected attribute operator or ']'".into()));
+            assert_eq!(error("a[href='x'"), (11, "expected ']'".into()));
+            assert_eq!(error("a[href='x]"), (8, "unterminated string".into()));
+            assert_eq!(error("a[href=]"), (8, "expected attribute value".into()));
+            assert_eq!(error("li:nth-child(x)"), (14, "invalid nth expression".into()));
+            assert_eq!(error("p::before"), (2, "pseudo-elements are not supported".into()));
+            assert_eq!(error("p:unknown"), (2, "unsupported pseudo-class :unknown".into()));
+            assert_eq!(error("p:not(:not(p))"), (7, ":not() cannot be nested".into()));
+            assert_eq!(error("p:has(a:has(b))"), (8, ":has() cannot be nested".into()));
+            assert_eq!(error("p:has()"), (7, "expected selector, found ')'".into()));
+            assert_eq!(error("p:has(> )"), (9, "expected selector, found ')'".into()));
+            assert_eq!(error("p:has(a"), (8, "expected ')'".into()));
+            assert_eq!(error("a)"), (2, "unexpected character ')'".into()));
+            assert_eq!(error("svg|rect"), (1, "namespace prefixes are not supported".into()));
+            assert_eq!(error("é .1"), (4, "expected identifier, found '1'".into()));
+            assert_eq!(error("a!"), (2, "unexpected character '!'".into()));
+            assert_eq!(error("[a=b c]"), (6, "unknown attribute flag c".into()));
+
+            assert_eq!(Selector::parse("a!").unwrap_err().to_string(),
+                       "unexpected character '!' at column 2");
+            assert!("a > b".parse::<Selector>().is_ok());
+            assert_eq!(Selector::parse("a >b").unwrap().to_string(), "a >b");
+            assert_eq!(Name("p").child(Selector::parse("a, b").unwrap()).describe().to_string(),
+                       "p > :is(a, b)");
+            assert!(document.select("a >").is_err());
+        }
+    }
+}